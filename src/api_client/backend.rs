@@ -0,0 +1,270 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use super::ApiError;
+
+/// A HTTP request of the API clients
+pub type HttpRequest = http::Request<Vec<u8>>;
+
+/// A HTTP response to a [`HttpRequest`]
+pub type HttpResponse = http::Response<Vec<u8>>;
+
+/// A way of sending HTTP requests
+///
+/// The API clients handle the CVR headers, rate limiting, retrying and
+/// unwrapping the responses on their own, so a backend only needs to send the
+/// requests as they are. `ReqwestBackend` is used by default with the
+/// `http_client` feature, `UreqBackend` with the `ureq` feature, and
+/// `FetchBackend` in browsers with the `web` feature.
+#[async_trait]
+pub trait HttpBackend: Send + Sync {
+	/// Sends the request, returning the response whatever its status is
+	///
+	/// # Errors
+	///
+	/// If a response wasn't received.
+	async fn execute(
+		&self, request: HttpRequest,
+	) -> Result<HttpResponse, HttpError>;
+}
+
+/// An error of a [`HttpBackend`], meaning that a response wasn't received
+#[derive(Debug)]
+pub struct HttpError {
+	source: Box<dyn std::error::Error + Send + Sync>,
+	transient: bool,
+}
+
+impl HttpError {
+	/// An error that isn't worth retrying
+	pub fn new(
+		source: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+	) -> Self {
+		Self { source: source.into(), transient: false }
+	}
+
+	/// An error that is worth retrying, like a timeout or a failed connection
+	pub fn transient(
+		source: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+	) -> Self {
+		Self { source: source.into(), transient: true }
+	}
+
+	/// If retrying the request could help
+	#[must_use]
+	pub const fn is_transient(&self) -> bool { self.transient }
+
+	/// The error of the backend
+	#[must_use]
+	pub fn into_inner(self) -> Box<dyn std::error::Error + Send + Sync> {
+		self.source
+	}
+}
+
+impl std::fmt::Display for HttpError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		self.source.fmt(f)
+	}
+}
+
+impl std::error::Error for HttpError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		Some(self.source.as_ref())
+	}
+}
+
+/// The backend that the API clients use unless they're given one
+#[cfg(all(feature = "http_client", not(target_arch = "wasm32")))]
+pub(super) fn default_backend() -> Result<Arc<dyn HttpBackend>, ApiError> {
+	let client = reqwest::Client::builder().build().map_err(HttpError::from)?;
+	Ok(Arc::new(ReqwestBackend::new(client)))
+}
+
+/// The backend that the API clients use unless they're given one
+#[cfg(all(
+	feature = "ureq",
+	not(feature = "http_client"),
+	not(target_arch = "wasm32")
+))]
+#[allow(clippy::unnecessary_wraps)]
+pub(super) fn default_backend() -> Result<Arc<dyn HttpBackend>, ApiError> {
+	Ok(Arc::new(UreqBackend::default()))
+}
+
+// reqwest isn't built for wasm32, so it can't be the default backend there
+#[cfg(all(
+	target_arch = "wasm32",
+	feature = "http_client",
+	not(feature = "web")
+))]
+compile_error!(
+	"The `http_client` feature doesn't provide a HTTP backend on wasm32, \
+	 enable the `web` feature for one that uses the fetch API of browsers"
+);
+
+/// The backend that the API clients use unless they're given one
+#[cfg(all(target_arch = "wasm32", feature = "web"))]
+#[allow(clippy::unnecessary_wraps)]
+pub(super) fn default_backend() -> Result<Arc<dyn HttpBackend>, ApiError> {
+	Ok(Arc::new(super::web::FetchBackend))
+}
+
+/// The backend that the API clients use unless they're given one
+#[cfg(any(
+	all(
+		not(target_arch = "wasm32"),
+		not(any(feature = "http_client", feature = "ureq"))
+	),
+	all(target_arch = "wasm32", not(feature = "web"))
+))]
+#[allow(clippy::unnecessary_wraps)]
+pub(super) fn default_backend() -> Result<Arc<dyn HttpBackend>, ApiError> {
+	Ok(Arc::new(Unconfigured))
+}
+
+/// Fails every request, for when there's no backend to use by default
+#[cfg(any(
+	all(
+		not(target_arch = "wasm32"),
+		not(any(feature = "http_client", feature = "ureq"))
+	),
+	all(target_arch = "wasm32", not(feature = "web"))
+))]
+struct Unconfigured;
+
+#[cfg(any(
+	all(
+		not(target_arch = "wasm32"),
+		not(any(feature = "http_client", feature = "ureq"))
+	),
+	all(target_arch = "wasm32", not(feature = "web"))
+))]
+#[async_trait]
+impl HttpBackend for Unconfigured {
+	async fn execute(
+		&self, _request: HttpRequest,
+	) -> Result<HttpResponse, HttpError> {
+		Err(HttpError::new(
+			"No HTTP backend was configured, enable the `http_client`, `ureq` or \
+			 `web` feature or add one with `with_http_backend`",
+		))
+	}
+}
+
+/// A backend using [`reqwest`]
+#[cfg(all(feature = "http_client", not(target_arch = "wasm32")))]
+#[derive(Debug, Clone)]
+pub struct ReqwestBackend {
+	client: reqwest::Client,
+}
+
+#[cfg(all(feature = "http_client", not(target_arch = "wasm32")))]
+impl ReqwestBackend {
+	/// Creates a backend that sends the requests with the client
+	#[must_use]
+	pub const fn new(client: reqwest::Client) -> Self { Self { client } }
+}
+
+#[cfg(all(feature = "http_client", not(target_arch = "wasm32")))]
+#[async_trait]
+impl HttpBackend for ReqwestBackend {
+	async fn execute(
+		&self, request: HttpRequest,
+	) -> Result<HttpResponse, HttpError> {
+		let request = reqwest::Request::try_from(request)?;
+		let response = self.client.execute(request).await?;
+
+		let mut builder = http::Response::builder()
+			.status(response.status())
+			.version(response.version());
+		if let Some(headers) = builder.headers_mut() {
+			headers.clone_from(response.headers());
+		}
+		let body = response.bytes().await?;
+		builder.body(body.into()).map_err(HttpError::new)
+	}
+}
+
+#[cfg(all(feature = "http_client", not(target_arch = "wasm32")))]
+impl From<reqwest::Error> for HttpError {
+	fn from(err: reqwest::Error) -> Self {
+		if err.is_timeout() || err.is_connect() {
+			Self::transient(err)
+		} else {
+			Self::new(err)
+		}
+	}
+}
+
+/// A backend using [`ureq`], which sends the requests on the blocking thread
+/// pool of tokio
+#[cfg(feature = "ureq")]
+#[derive(Debug, Clone)]
+pub struct UreqBackend {
+	agent: ureq::Agent,
+}
+
+#[cfg(feature = "ureq")]
+impl UreqBackend {
+	/// Creates a backend that sends the requests with the agent
+	///
+	/// The agent should be configured to not treat error statuses as errors,
+	/// as otherwise the bodies and headers of the error responses are lost.
+	#[must_use]
+	pub const fn new(agent: ureq::Agent) -> Self { Self { agent } }
+}
+
+#[cfg(feature = "ureq")]
+impl Default for UreqBackend {
+	fn default() -> Self {
+		Self::new(
+			ureq::Agent::config_builder().http_status_as_error(false).build().into(),
+		)
+	}
+}
+
+#[cfg(feature = "ureq")]
+#[async_trait]
+impl HttpBackend for UreqBackend {
+	async fn execute(
+		&self, request: HttpRequest,
+	) -> Result<HttpResponse, HttpError> {
+		let agent = self.agent.clone();
+		tokio::task::spawn_blocking(move || {
+			let (parts, body) = request.into_parts();
+			let response = if body.is_empty() {
+				agent.run(http::Request::from_parts(parts, ureq::SendBody::none()))
+			} else {
+				agent.run(http::Request::from_parts(parts, body))
+			};
+			let response = match response {
+				Ok(response) => response,
+				// Only happens if the agent treats error statuses as errors
+				Err(ureq::Error::StatusCode(status)) => {
+					return http::Response::builder()
+						.status(status)
+						.body(Vec::new())
+						.map_err(HttpError::new);
+				}
+				Err(err) => return Err(err.into()),
+			};
+			let (parts, mut body) = response.into_parts();
+			Ok(http::Response::from_parts(parts, body.read_to_vec()?))
+		})
+		.await
+		.map_err(HttpError::new)?
+	}
+}
+
+#[cfg(feature = "ureq")]
+impl From<ureq::Error> for HttpError {
+	fn from(err: ureq::Error) -> Self {
+		match err {
+			ureq::Error::Timeout(_)
+			| ureq::Error::ConnectionFailed
+			| ureq::Error::Io(_) => Self::transient(err),
+			err => Self::new(err),
+		}
+	}
+}
@@ -0,0 +1,31 @@
+use std::time::Duration;
+
+/// How long to wait before the attempt number `attempt`, starting from 1,
+/// growing exponentially from `initial` up to `max`
+pub fn exponential(
+	initial: Duration, max: Duration, multiplier: f64, jitter: f64, attempt: u32,
+) -> Duration {
+	let exponent = i32::try_from(attempt.saturating_sub(1)).unwrap_or(i32::MAX);
+	let delay = initial.as_secs_f64() * multiplier.max(1.0).powi(exponent);
+	let delay = delay.min(max.as_secs_f64());
+	let jitter = delay * jitter.clamp(0.0, 1.0) * random_fraction();
+	Duration::try_from_secs_f64(delay + jitter).unwrap_or(max)
+}
+
+/// A random number between 0 and 1, good enough for jittering delays
+fn random_fraction() -> f64 {
+	use std::hash::{BuildHasher, Hasher};
+
+	let mut hasher =
+		std::collections::hash_map::RandomState::new().build_hasher();
+	hasher.write_u128(
+		super::rt::SystemTime::now()
+			.duration_since(super::rt::UNIX_EPOCH)
+			.unwrap_or_default()
+			.as_nanos(),
+	);
+	// Precision doesn't matter for jitter
+	#[allow(clippy::cast_precision_loss)]
+	let fraction = hasher.finish() as f64 / u64::MAX as f64;
+	fraction
+}
@@ -0,0 +1,209 @@
+//! Synchronous versions of the API clients, in the spirit of
+//! `reqwest::blocking`.
+//!
+//! Each client runs the async one on a tokio runtime of its own, which is
+//! shared with the clients that are created from it by logging in or
+//! upgrading for example. Only HTTP queries are available, as the WS API
+//! needs a runtime that runs in the background.
+//!
+//! The clients must not be used or dropped from within an async runtime, as
+//! their runtimes can't be nested in another one.
+//!
+//! ```no_run
+//! # fn example() -> Result<(), chilloutvr::api_client::ApiError> {
+//! use chilloutvr::{
+//! 	api_client::{ApiConfiguration, blocking::UnauthenticatedCVR},
+//! 	query::LoginCredentials,
+//! };
+//!
+//! let api_client =
+//! 	UnauthenticatedCVR::new(ApiConfiguration::new("example".to_owned()))?;
+//! let (api_client, _user) = api_client.login(LoginCredentials {
+//! 	email: "email@address".to_owned(),
+//! 	password: "pa$$word".to_owned(),
+//! })?;
+//! let friends = api_client.query(chilloutvr::query::FriendList())?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::sync::Arc;
+
+use tokio::runtime::Runtime;
+
+use super::{ApiConfiguration, ApiError, NoAuthentication};
+use crate::{
+	model::UserAuth,
+	query::{AuthType, SavedLoginCredentials},
+};
+
+fn runtime() -> Result<Arc<Runtime>, ApiError> {
+	tokio::runtime::Builder::new_current_thread()
+		.enable_all()
+		.build()
+		.map(Arc::new)
+		.map_err(ApiError::Runtime)
+}
+
+/// A synchronous version of [`super::UnauthenticatedCVR`]
+pub struct UnauthenticatedCVR {
+	inner: super::UnauthenticatedCVR,
+	runtime: Arc<Runtime>,
+}
+
+impl std::fmt::Debug for UnauthenticatedCVR {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("UnauthenticatedCVR").finish_non_exhaustive()
+	}
+}
+
+impl UnauthenticatedCVR {
+	/// Creates an unauthenticated API client
+	///
+	/// # Errors
+	///
+	/// If deserializing user agent into a header fails, or if creating the
+	/// runtime fails.
+	pub fn new(config: ApiConfiguration) -> Result<Self, ApiError> {
+		Self::from_async(super::UnauthenticatedCVR::new(config)?)
+	}
+
+	/// Wraps an async API client, for example to use a cache or middleware
+	///
+	/// # Errors
+	///
+	/// If creating the runtime fails.
+	pub fn from_async(
+		inner: super::UnauthenticatedCVR,
+	) -> Result<Self, ApiError> {
+		Ok(Self { inner, runtime: runtime()? })
+	}
+
+	/// The async API client that this one wraps
+	#[must_use]
+	pub const fn as_async(&self) -> &super::UnauthenticatedCVR { &self.inner }
+
+	/// Sends a query to the CVR API, see [`super::UnauthenticatedCVR::query`]
+	///
+	/// # Errors
+	///
+	/// If the request fails, the API responds with an error, or the response
+	/// can't be deserialized.
+	pub fn query<ReturnType, FromState, QueryableType>(
+		&self, queryable: QueryableType,
+	) -> Result<ReturnType, ApiError>
+	where
+		ReturnType: serde::de::DeserializeOwned,
+		FromState: racal::FromApiState<NoAuthentication>,
+		QueryableType: racal::Queryable<FromState, ReturnType> + Send + Sync,
+	{
+		self.runtime.block_on(self.inner.query(queryable))
+	}
+
+	/// Logs in, see [`super::UnauthenticatedCVR::login`]
+	///
+	/// # Errors
+	///
+	/// If the API rejects the credentials, [`ApiError::InvalidCredentials`],
+	/// if the request fails otherwise, or if saving the credentials fails.
+	pub fn login(
+		&self, auth: impl Into<AuthType> + Send,
+	) -> Result<(AuthenticatedCVR, UserAuth), ApiError> {
+		let (inner, user_auth) = self.runtime.block_on(self.inner.login(auth))?;
+		Ok((AuthenticatedCVR { inner, runtime: self.runtime.clone() }, user_auth))
+	}
+
+	/// Adds authentication to the API client, see
+	/// [`super::UnauthenticatedCVR::upgrade`]
+	///
+	/// # Errors
+	///
+	/// If deserializing user agent or authentication fails, or if saving the
+	/// credentials fails.
+	pub fn upgrade(
+		self, auth: impl Into<SavedLoginCredentials> + Send,
+	) -> Result<AuthenticatedCVR, ApiError> {
+		Ok(AuthenticatedCVR {
+			inner: self.inner.upgrade(auth)?,
+			runtime: self.runtime,
+		})
+	}
+}
+
+/// A synchronous version of [`super::AuthenticatedCVR`]
+pub struct AuthenticatedCVR {
+	inner: super::AuthenticatedCVR,
+	runtime: Arc<Runtime>,
+}
+
+impl std::fmt::Debug for AuthenticatedCVR {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("AuthenticatedCVR").finish_non_exhaustive()
+	}
+}
+
+impl AuthenticatedCVR {
+	/// Creates an API client
+	///
+	/// # Errors
+	///
+	/// If deserializing user agent or authentication into headers fails, or if
+	/// creating the runtime fails.
+	pub fn new(
+		config: ApiConfiguration, auth: impl Into<SavedLoginCredentials> + Send,
+	) -> Result<Self, ApiError> {
+		Self::from_async(super::AuthenticatedCVR::new(config, auth)?)
+	}
+
+	/// Wraps an async API client, for example to use a cache, middleware or
+	/// re-authentication
+	///
+	/// # Errors
+	///
+	/// If creating the runtime fails.
+	pub fn from_async(inner: super::AuthenticatedCVR) -> Result<Self, ApiError> {
+		Ok(Self { inner, runtime: runtime()? })
+	}
+
+	/// The async API client that this one wraps
+	#[must_use]
+	pub const fn as_async(&self) -> &super::AuthenticatedCVR { &self.inner }
+
+	/// The authentication that the client was created with
+	#[must_use]
+	pub const fn state(&self) -> &SavedLoginCredentials { self.inner.state() }
+
+	/// The credentials that the client is currently using, see
+	/// [`super::AuthenticatedCVR::credentials`]
+	#[must_use]
+	pub fn credentials(&self) -> SavedLoginCredentials {
+		self.inner.credentials()
+	}
+
+	/// Sends a query to the CVR API, see [`super::AuthenticatedCVR::query`]
+	///
+	/// # Errors
+	///
+	/// If the request fails, the API responds with an error, or the response
+	/// can't be deserialized.
+	pub fn query<ReturnType, FromState, QueryableType>(
+		&self, queryable: QueryableType,
+	) -> Result<ReturnType, ApiError>
+	where
+		ReturnType: serde::de::DeserializeOwned,
+		FromState: racal::FromApiState<SavedLoginCredentials>,
+		QueryableType: racal::Queryable<FromState, ReturnType> + Send + Sync,
+	{
+		self.runtime.block_on(self.inner.query(queryable))
+	}
+
+	/// Removes the authentication from the API client
+	///
+	/// # Errors
+	///
+	/// If creating the unauthenticated HTTP client fails
+	pub fn downgrade(self) -> Result<UnauthenticatedCVR, ApiError> {
+		let inner = self.runtime.block_on(self.inner.downgrade())?;
+		Ok(UnauthenticatedCVR { inner, runtime: self.runtime })
+	}
+}
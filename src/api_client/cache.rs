@@ -0,0 +1,162 @@
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex, MutexGuard},
+	time::Duration,
+};
+
+use super::rt::Instant;
+
+/// A cache of the responses of read-only HTTP queries
+///
+/// Responses are cached per query URL & authenticated user, for as long as the
+/// TTL of their query type is. Query types without a TTL aren't cached at all,
+/// unless a default TTL is set. Cloning this returns a handle to the same
+/// cache, so it can be shared between multiple API clients.
+///
+/// ```
+/// # use std::time::Duration;
+/// use chilloutvr::{api_client::ResponseCache, query};
+///
+/// let cache = ResponseCache::new(1000)
+/// 	.with_ttl::<query::UserDetails>(Duration::from_secs(60))
+/// 	.with_ttl::<query::Categories>(Duration::from_secs(60 * 60));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+	inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug)]
+struct Inner {
+	max_entries: usize,
+	default_ttl: Option<Duration>,
+	ttls: HashMap<&'static str, Duration>,
+	entries: HashMap<Key, Entry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Key {
+	/// The user that the response is for, empty for unauthenticated queries
+	scope: String,
+	url: String,
+}
+
+#[derive(Debug)]
+struct Entry {
+	query_type: &'static str,
+	body: Arc<[u8]>,
+	inserted: Instant,
+	expires: Instant,
+}
+
+impl ResponseCache {
+	/// Creates an empty cache that holds at most `max_entries` responses
+	#[must_use]
+	pub fn new(max_entries: usize) -> Self {
+		Self {
+			inner: Arc::new(Mutex::new(Inner {
+				max_entries,
+				default_ttl: None,
+				ttls: HashMap::new(),
+				entries: HashMap::new(),
+			})),
+		}
+	}
+
+	fn inner(&self) -> MutexGuard<'_, Inner> {
+		self.inner.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+	}
+
+	/// Caches the responses of every query type without a TTL of its own
+	#[must_use]
+	pub fn with_default_ttl(self, ttl: Duration) -> Self {
+		self.inner().default_ttl = Some(ttl);
+		self
+	}
+
+	/// Caches the responses of a query type for the TTL
+	#[must_use]
+	pub fn with_ttl<QueryType>(self, ttl: Duration) -> Self {
+		self.inner().ttls.insert(std::any::type_name::<QueryType>(), ttl);
+		self
+	}
+
+	/// How many responses are currently cached, including expired ones
+	#[must_use]
+	pub fn len(&self) -> usize { self.inner().entries.len() }
+
+	/// If there are no responses cached
+	#[must_use]
+	pub fn is_empty(&self) -> bool { self.inner().entries.is_empty() }
+
+	/// Removes every cached response
+	pub fn clear(&self) { self.inner().entries.clear(); }
+
+	/// Removes the cached responses of a query type
+	pub fn invalidate<QueryType>(&self) {
+		let query_type = std::any::type_name::<QueryType>();
+		self.inner().entries.retain(|_, entry| entry.query_type != query_type);
+	}
+
+	/// Removes the cached responses of an URL, for every user
+	///
+	/// The URL is the one the query was sent to, so with the configured
+	/// [`http_url`](super::ApiConfiguration::http_url) as its base.
+	pub fn invalidate_url(&self, url: &str) {
+		self.inner().entries.retain(|key, _| key.url != url);
+	}
+
+	/// If responses of the query type are cached at all
+	pub(super) fn caches<QueryType>(&self) -> bool {
+		let inner = self.inner();
+		inner.default_ttl.is_some()
+			|| inner.ttls.contains_key(std::any::type_name::<QueryType>())
+	}
+
+	pub(super) fn get(&self, scope: &str, url: &str) -> Option<Arc<[u8]>> {
+		let key = Key { scope: scope.to_owned(), url: url.to_owned() };
+		let mut inner = self.inner();
+		let entry = inner.entries.get(&key)?;
+		if entry.expires > Instant::now() {
+			return Some(entry.body.clone());
+		}
+		inner.entries.remove(&key);
+		drop(inner);
+		None
+	}
+
+	pub(super) fn insert<QueryType>(
+		&self, scope: &str, url: &str, body: Arc<[u8]>,
+	) {
+		let query_type = std::any::type_name::<QueryType>();
+		let mut inner = self.inner();
+		let Some(ttl) = inner.ttls.get(query_type).copied().or(inner.default_ttl)
+		else {
+			return;
+		};
+		if inner.max_entries == 0 {
+			return;
+		}
+
+		let now = Instant::now();
+		if inner.entries.len() >= inner.max_entries {
+			inner.entries.retain(|_, entry| entry.expires > now);
+		}
+		while inner.entries.len() >= inner.max_entries {
+			let Some(oldest) = inner
+				.entries
+				.iter()
+				.min_by_key(|(_, entry)| entry.inserted)
+				.map(|(key, _)| key.clone())
+			else {
+				break;
+			};
+			inner.entries.remove(&oldest);
+		}
+
+		inner.entries.insert(
+			Key { scope: scope.to_owned(), url: url.to_owned() },
+			Entry { query_type, body, inserted: now, expires: now + ttl },
+		);
+	}
+}
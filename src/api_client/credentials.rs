@@ -0,0 +1,234 @@
+use std::{
+	collections::BTreeMap,
+	io,
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex},
+};
+
+use crate::query::SavedLoginCredentials;
+
+/// Somewhere to keep the access keys of accounts between runs
+///
+/// Each account is stored with a name of the user's choosing, so that
+/// multiple accounts can share a store.
+pub trait CredentialStore: Send + Sync {
+	/// Loads the credentials of an account, `None` if there aren't any stored
+	///
+	/// # Errors
+	///
+	/// If reading the store fails.
+	fn load(&self, account: &str) -> io::Result<Option<SavedLoginCredentials>>;
+
+	/// Stores the credentials of an account, replacing the previous ones
+	///
+	/// # Errors
+	///
+	/// If writing to the store fails.
+	fn save(
+		&self, account: &str, credentials: &SavedLoginCredentials,
+	) -> io::Result<()>;
+
+	/// Removes the credentials of an account, if there are any stored
+	///
+	/// # Errors
+	///
+	/// If writing to the store fails.
+	fn remove(&self, account: &str) -> io::Result<()>;
+
+	/// The names of the accounts that have credentials stored
+	///
+	/// # Errors
+	///
+	/// If reading the store fails.
+	fn accounts(&self) -> io::Result<Vec<String>>;
+}
+
+/// Stores credentials in a JSON file
+///
+/// The file is only readable & writable by its owner on Unix systems, and is
+/// replaced atomically on every write.
+///
+/// A file with the response of logging in, like the `user-auth.json` that the
+/// tests use, is read as a single account named after its username. It's
+/// rewritten with the accounts in the new format on the next write.
+#[derive(Debug)]
+pub struct FileCredentialStore {
+	path: PathBuf,
+	/// Serializes the read-modify-write cycles of this process
+	lock: Mutex<()>,
+}
+
+impl FileCredentialStore {
+	/// Creates a store for the file at the path, which doesn't need to exist yet
+	#[must_use]
+	pub fn new(path: impl Into<PathBuf>) -> Self {
+		Self { path: path.into(), lock: Mutex::new(()) }
+	}
+
+	/// The path of the file that the credentials are stored in
+	#[must_use]
+	pub fn path(&self) -> &Path { &self.path }
+
+	fn read(&self) -> io::Result<BTreeMap<String, SavedLoginCredentials>> {
+		match std::fs::read(&self.path) {
+			Ok(bytes) => Ok(serde_json::from_slice::<StoredFile>(&bytes)?.into()),
+			Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+			Err(err) => Err(err),
+		}
+	}
+
+	fn write(
+		&self, accounts: &BTreeMap<String, SavedLoginCredentials>,
+	) -> io::Result<()> {
+		use std::io::Write;
+
+		let mut temp_path = self.path.clone().into_os_string();
+		temp_path.push(".tmp");
+		let temp_path = PathBuf::from(temp_path);
+
+		let mut options = std::fs::OpenOptions::new();
+		options.write(true).create(true).truncate(true);
+		#[cfg(unix)]
+		std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+		{
+			let mut file = options.open(&temp_path)?;
+			// The mode only applies to new files, not to leftovers of failed writes
+			#[cfg(unix)]
+			file
+				.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+			file.write_all(&serde_json::to_vec_pretty(accounts)?)?;
+			file.sync_all()?;
+		}
+
+		std::fs::rename(temp_path, &self.path)
+	}
+
+	fn modify(
+		&self, modify: impl FnOnce(&mut BTreeMap<String, SavedLoginCredentials>),
+	) -> io::Result<()> {
+		let _guard =
+			self.lock.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+		let mut accounts = self.read()?;
+		modify(&mut accounts);
+		self.write(&accounts)
+	}
+}
+
+/// The contents of a credentials file
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum StoredFile {
+	Accounts(BTreeMap<String, SavedLoginCredentials>),
+	/// The response of logging in, from before multiple accounts were supported
+	Login {
+		data: LoginResponse,
+	},
+}
+
+/// The parts of a login response that are needed for the credentials
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LoginResponse {
+	username: String,
+	access_key: String,
+}
+
+impl From<StoredFile> for BTreeMap<String, SavedLoginCredentials> {
+	fn from(file: StoredFile) -> Self {
+		match file {
+			StoredFile::Accounts(accounts) => accounts,
+			StoredFile::Login { data: LoginResponse { username, access_key } } => {
+				Self::from([(
+					username.clone(),
+					SavedLoginCredentials { username, access_key },
+				)])
+			}
+		}
+	}
+}
+
+impl CredentialStore for FileCredentialStore {
+	fn load(&self, account: &str) -> io::Result<Option<SavedLoginCredentials>> {
+		Ok(self.read()?.remove(account))
+	}
+
+	fn save(
+		&self, account: &str, credentials: &SavedLoginCredentials,
+	) -> io::Result<()> {
+		self.modify(|accounts| {
+			accounts.insert(account.to_owned(), credentials.clone());
+		})
+	}
+
+	fn remove(&self, account: &str) -> io::Result<()> {
+		self.modify(|accounts| {
+			accounts.remove(account);
+		})
+	}
+
+	fn accounts(&self) -> io::Result<Vec<String>> {
+		Ok(self.read()?.into_keys().collect())
+	}
+}
+
+/// Stores credentials in memory, mostly useful for testing
+#[derive(Debug, Default)]
+pub struct MemoryCredentialStore {
+	accounts: Mutex<BTreeMap<String, SavedLoginCredentials>>,
+}
+
+impl MemoryCredentialStore {
+	fn lock(
+		&self,
+	) -> std::sync::MutexGuard<'_, BTreeMap<String, SavedLoginCredentials>> {
+		self.accounts.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+	}
+}
+
+impl CredentialStore for MemoryCredentialStore {
+	fn load(&self, account: &str) -> io::Result<Option<SavedLoginCredentials>> {
+		Ok(self.lock().get(account).cloned())
+	}
+
+	fn save(
+		&self, account: &str, credentials: &SavedLoginCredentials,
+	) -> io::Result<()> {
+		self.lock().insert(account.to_owned(), credentials.clone());
+		Ok(())
+	}
+
+	fn remove(&self, account: &str) -> io::Result<()> {
+		self.lock().remove(account);
+		Ok(())
+	}
+
+	fn accounts(&self) -> io::Result<Vec<String>> {
+		Ok(self.lock().keys().cloned().collect())
+	}
+}
+
+/// An account in a credential store that a client loads & saves its
+/// credentials from
+#[derive(Clone)]
+pub struct StoredAccount {
+	pub store: Arc<dyn CredentialStore>,
+	pub account: String,
+}
+
+impl std::fmt::Debug for StoredAccount {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("StoredAccount")
+			.field("account", &self.account)
+			.finish_non_exhaustive()
+	}
+}
+
+impl StoredAccount {
+	pub fn load(&self) -> io::Result<Option<SavedLoginCredentials>> {
+		self.store.load(&self.account)
+	}
+
+	pub fn save(&self, credentials: &SavedLoginCredentials) -> io::Result<()> {
+		self.store.save(&self.account, credentials)
+	}
+}
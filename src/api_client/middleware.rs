@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use super::ApiError;
+
+/// Information about a received HTTP response
+#[cfg(feature = "http_client_core")]
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ResponseInfo<'a> {
+	/// The HTTP method of the request
+	pub method: &'a http::Method,
+	/// The URL that the request was sent to
+	pub url: &'a http::Uri,
+	/// The HTTP status code of the response
+	pub status: http::StatusCode,
+	/// The headers of the response
+	pub headers: &'a http::HeaderMap,
+	/// The body of the response
+	pub body: &'a [u8],
+	/// How long it took from sending the request to receiving the whole body
+	pub elapsed: std::time::Duration,
+}
+
+/// Hooks into the requests & responses of the API clients
+///
+/// Useful for logging, metrics, extra headers or request signing for example.
+/// Every method does nothing by default, so only the needed ones have to be
+/// implemented. Middleware is run in the order it was added in.
+#[async_trait::async_trait]
+pub trait Middleware: Send + Sync {
+	/// Called right before a HTTP request is sent, after it's rate limited
+	#[cfg(feature = "http_client_core")]
+	async fn before_request(&self, _request: &mut super::HttpRequest) {}
+
+	/// Called when a HTTP response is received, for every retry separately
+	#[cfg(feature = "http_client_core")]
+	fn after_response(&self, _response: &ResponseInfo<'_>) {}
+
+	/// Called when a query fails, or when the WS connection drops or fails to
+	/// be re-established
+	///
+	/// A query that's replayed after re-authenticating only counts as failing
+	/// if the replay does, or if logging in again fails.
+	fn on_error(&self, _error: &ApiError) {}
+
+	/// Called right before a WS frame is sent
+	#[cfg(feature = "ws_client")]
+	fn on_ws_send(&self, _frame: &[u8]) {}
+
+	/// Called when a WS frame is received, before it's passed on to listeners
+	#[cfg(feature = "ws_client")]
+	fn on_ws_receive(&self, _frame: &[u8]) {}
+}
+
+/// The middleware of an API client
+#[derive(Clone, Default)]
+pub struct Chain(Arc<Vec<Arc<dyn Middleware>>>);
+
+impl std::fmt::Debug for Chain {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("Chain").field("len", &self.0.len()).finish()
+	}
+}
+
+impl Chain {
+	pub fn push(&mut self, middleware: Arc<dyn Middleware>) {
+		Arc::make_mut(&mut self.0).push(middleware);
+	}
+
+	#[cfg(feature = "http_client_core")]
+	pub async fn before_request(&self, request: &mut super::HttpRequest) {
+		for middleware in self.0.iter() {
+			middleware.before_request(request).await;
+		}
+	}
+
+	#[cfg(feature = "http_client_core")]
+	pub fn after_response(&self, response: &ResponseInfo<'_>) {
+		for middleware in self.0.iter() {
+			middleware.after_response(response);
+		}
+	}
+
+	pub fn on_error(&self, error: &ApiError) {
+		for middleware in self.0.iter() {
+			middleware.on_error(error);
+		}
+	}
+
+	#[cfg(feature = "ws_client")]
+	pub fn on_ws_send(&self, frame: &[u8]) {
+		for middleware in self.0.iter() {
+			middleware.on_ws_send(frame);
+		}
+	}
+
+	#[cfg(feature = "ws_client")]
+	pub fn on_ws_receive(&self, frame: &[u8]) {
+		for middleware in self.0.iter() {
+			middleware.on_ws_receive(frame);
+		}
+	}
+}
@@ -1,9 +1,14 @@
-//! An optional API client feature using `reqwest`
+//! An optional API client feature, using `reqwest` by default
 //!
-//! Besides using this, you could instead easily implement your own client using
-//! a different HTTP library with the [`racal::Queryable`](racal::Queryable)
-//! trait. Though this does additionally support unwrapping the message/data of
-//! the CVR API responses.
+//! The HTTP client sends its requests with a [`HttpBackend`], which can be
+//! swapped out for [`UreqBackend`] or a custom one, with the client still
+//! handling the CVR headers, rate limiting and unwrapping the message/data of
+//! the CVR API responses. You could also implement your own client entirely
+//! with the [`racal::Queryable`](racal::Queryable) trait.
+//!
+//! The clients used to implement `racal::reqwest::ApiClient`, which was
+//! dropped along with the hard dependency on `reqwest`. Their `query` methods
+//! take the same requests.
 //!
 //! If you're implementing your own API client, you need to implement two
 //! possible API states:
@@ -20,31 +25,105 @@
 //!
 //! The WebSocket API client is more messy, in this implementation the
 //! connection is opened lazily (on first use) and never manually closed again
-//! afterwards.
-
-#[cfg(feature = "http_client")]
-use std::num::NonZeroU32;
-
-#[cfg(feature = "http_client")]
-use governor::{
-	Quota,
-	RateLimiter,
-	clock::DefaultClock,
-	middleware::NoOpMiddleware,
-	state::{InMemoryState, NotKeyed},
-};
+//! afterwards. If the connection drops, it's re-established in the background
+//! as per the configured [`ReconnectPolicy`].
+
+#[cfg(any(feature = "http_client_core", feature = "ws_client"))]
+use std::sync::Arc;
+
+#[cfg(feature = "http_client_core")]
+use http::HeaderMap;
 use http::{HeaderName, HeaderValue, header::InvalidHeaderValue};
-#[cfg(feature = "http_client")]
-pub use racal::reqwest::ApiClient;
-#[cfg(feature = "http_client")]
-use reqwest::{Client, RequestBuilder, header::HeaderMap};
 
-#[cfg(feature = "http_client")]
-use crate::query::NoAuthentication;
 use crate::query::SavedLoginCredentials;
+#[cfg(feature = "http_client_core")]
+use crate::{
+	model::UserAuth,
+	query::{AuthType, NoAuthentication},
+};
 
+#[cfg(feature = "http_client_core")]
+mod backend;
+#[cfg(any(feature = "http_client_core", feature = "ws_client"))]
+mod backoff;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+#[cfg(feature = "http_client_core")]
+mod cache;
+#[cfg(any(feature = "http_client_core", feature = "ws_client"))]
+mod credentials;
+#[cfg(any(feature = "http_client_core", feature = "ws_client"))]
+mod middleware;
+#[cfg(any(feature = "http_client_core", feature = "ws_client"))]
+mod rate_limit;
+#[cfg(feature = "http_client_core")]
+mod retry;
+#[cfg(any(feature = "http_client_core", feature = "ws_client"))]
+mod rt;
+#[cfg(feature = "ws_client")]
+mod transport;
+#[cfg(all(target_arch = "wasm32", feature = "web"))]
+mod web;
 #[cfg(feature = "ws_client")]
 mod ws;
+#[cfg(all(feature = "http_client", not(target_arch = "wasm32")))]
+pub use backend::ReqwestBackend;
+#[cfg(feature = "ureq")]
+pub use backend::UreqBackend;
+#[cfg(feature = "http_client_core")]
+pub use backend::{HttpBackend, HttpError, HttpRequest, HttpResponse};
+#[cfg(feature = "http_client_core")]
+pub use cache::ResponseCache;
+#[cfg(any(feature = "http_client_core", feature = "ws_client"))]
+use credentials::StoredAccount;
+#[cfg(any(feature = "http_client_core", feature = "ws_client"))]
+pub use credentials::{
+	CredentialStore,
+	FileCredentialStore,
+	MemoryCredentialStore,
+};
+#[cfg(any(feature = "http_client_core", feature = "ws_client"))]
+pub use middleware::Middleware;
+#[cfg(feature = "http_client_core")]
+pub use middleware::ResponseInfo;
+#[cfg(any(feature = "http_client_core", feature = "ws_client"))]
+pub use rate_limit::{RateLimitQuota, RateLimits, SharedRateLimiter};
+#[cfg(any(feature = "http_client_core", feature = "ws_client"))]
+use rate_limit::{RateLimiters, Route};
+#[cfg(feature = "http_client_core")]
+pub use retry::RetryPolicy;
+#[cfg(all(feature = "ws_client", not(target_arch = "wasm32")))]
+pub use transport::EzsocketsTransport;
+#[cfg(feature = "ws_client")]
+pub use transport::{
+	MemoryPeer,
+	MemoryServer,
+	MemoryTransport,
+	WsConnection,
+	WsFrame,
+	WsTransport,
+};
+#[cfg(all(
+	target_arch = "wasm32",
+	feature = "web",
+	feature = "http_client_core"
+))]
+pub use web::FetchBackend;
+#[cfg(all(
+	target_arch = "wasm32",
+	feature = "web",
+	feature = "ws_client"
+))]
+pub use web::WebSocketTransport;
+#[cfg(feature = "ws_client")]
+pub use ws::{
+	ConnectionState,
+	ReconnectPolicy,
+	WsListener,
+	WsOutcome,
+	WsQueueOverflow,
+	WsQueuePolicy,
+};
 
 /// Configuration for the API client
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -59,8 +138,54 @@ pub struct ApiConfiguration {
 	pub platform: String,
 	/// A comma separated string of compatible (API?) versions
 	pub compatible_versions: String,
+	/// The base URL of the HTTP API, without a trailing slash.
+	///
+	/// Queries build their URLs on top of
+	/// [`API_V1_HTTP_URL`](crate::API_V1_HTTP_URL), which gets swapped for this
+	/// one when they're sent.
+	#[serde(default = "default_http_url")]
+	pub http_url: String,
+	/// The URL of the WS API
+	#[serde(default = "default_ws_url")]
+	pub ws_url: String,
+	/// How many requests the API client is allowed to send, unless it was
+	/// created with a [`SharedRateLimiter`]
+	#[cfg(any(feature = "http_client_core", feature = "ws_client"))]
+	#[serde(default)]
+	pub rate_limits: RateLimits,
+	/// How failed HTTP queries should be retried
+	#[cfg(feature = "http_client_core")]
+	#[serde(default)]
+	pub retry: RetryPolicy,
+	/// How the WS connection should be re-established if it drops
+	#[cfg(feature = "ws_client")]
+	#[serde(default)]
+	pub ws_reconnect: ReconnectPolicy,
+	/// How WS messages are queued while the connection is being re-established
+	#[cfg(feature = "ws_client")]
+	#[serde(default)]
+	pub ws_queue: WsQueuePolicy,
+	/// How many received WS messages to buffer for each listener, before the
+	/// oldest ones are skipped
+	#[cfg(feature = "ws_client")]
+	#[serde(default = "default_ws_listener_capacity")]
+	pub ws_listener_capacity: usize,
+	/// How often to automatically mark the user as online while the WS
+	/// connection is open, `None` to not do it at all.
+	///
+	/// The CVR API seems to expect this about every 30 seconds.
+	#[cfg(feature = "ws_client")]
+	#[serde(default)]
+	pub ws_keepalive_interval: Option<std::time::Duration>,
 }
 
+fn default_http_url() -> String { crate::API_V1_HTTP_URL.to_string() }
+
+fn default_ws_url() -> String { crate::API_V1_WS_URL.to_string() }
+
+#[cfg(feature = "ws_client")]
+const fn default_ws_listener_capacity() -> usize { 256 }
+
 impl ApiConfiguration {
 	/// Creates a new API client configuration
 	#[must_use]
@@ -70,9 +195,32 @@ impl ApiConfiguration {
 			mature_content_enabled: false,
 			platform: "pc_standalone".to_string(),
 			compatible_versions: "0,1,2".to_string(),
+			http_url: default_http_url(),
+			ws_url: default_ws_url(),
+			#[cfg(any(feature = "http_client_core", feature = "ws_client"))]
+			rate_limits: RateLimits::default(),
+			#[cfg(feature = "http_client_core")]
+			retry: RetryPolicy::default(),
+			#[cfg(feature = "ws_client")]
+			ws_reconnect: ReconnectPolicy::default(),
+			#[cfg(feature = "ws_client")]
+			ws_queue: WsQueuePolicy::default(),
+			#[cfg(feature = "ws_client")]
+			ws_listener_capacity: default_ws_listener_capacity(),
+			#[cfg(feature = "ws_client")]
+			ws_keepalive_interval: None,
 		}
 	}
 
+	/// Points an URL that was built for the default API to the configured one
+	/// instead
+	#[cfg(feature = "http_client_core")]
+	fn rebase_url(&self, url: &str) -> String {
+		url
+			.strip_prefix(crate::API_V1_HTTP_URL)
+			.map_or_else(|| url.to_owned(), |path| self.http_url.clone() + path)
+	}
+
 	fn to_headers(
 		&self,
 	) -> Result<Vec<(HeaderName, HeaderValue)>, InvalidHeaderValue> {
@@ -108,126 +256,564 @@ pub enum ApiError {
 	/// An error happened with serialization
 	Serde(serde_json::Error),
 	/// An error happened with the HTTPS request
-	#[cfg(feature = "http_client")]
-	Http(reqwest::Error),
+	#[cfg(feature = "http_client_core")]
+	Http(HttpError),
 	/// An error happened with the WS connection
 	#[cfg(feature = "ws_client")]
-	WebSocket(ezsockets::Error),
+	WebSocket(Box<dyn std::error::Error + Send + Sync>),
+	/// A WS listener fell behind, and skipped over this many messages
+	#[cfg(feature = "ws_client")]
+	Lagged(u64),
+	/// The API responded with an error
+	#[cfg(feature = "http_client_core")]
+	Api {
+		/// The HTTP status code of the response
+		status: http::StatusCode,
+		/// The message that the API responded with
+		message: String,
+	},
+	/// The API rejected the credentials that were used to login with
+	#[cfg(feature = "http_client_core")]
+	InvalidCredentials(String),
+	/// Loading or saving credentials failed
+	#[cfg(any(feature = "http_client_core", feature = "ws_client"))]
+	CredentialStore(std::io::Error),
+	/// The API responded to a WS request with a popup instead of confirming it
+	#[cfg(feature = "ws_client")]
+	Rejected(Box<crate::model::WsResponse>),
+	/// The API didn't respond to a WS request in time
+	#[cfg(feature = "ws_client")]
+	Timeout,
+	/// Creating the runtime of a blocking API client failed
+	#[cfg(feature = "blocking")]
+	Runtime(std::io::Error),
 }
 
-impl From<serde_json::Error> for ApiError {
-	fn from(err: serde_json::Error) -> Self { Self::Serde(err) }
+impl ApiError {
+	/// Parses an error response of the API
+	#[cfg(feature = "http_client_core")]
+	#[must_use]
+	pub fn from_response(status: http::StatusCode, body: &[u8]) -> Self {
+		let message = serde_json::from_slice::<
+			crate::model::ResponseDataWrapper<Option<serde_json::Value>>,
+		>(body)
+		.map_or_else(
+			|_| String::from_utf8_lossy(body).into_owned(),
+			|wrapper| wrapper.message,
+		);
+		Self::Api { status, message }
+	}
+
+	/// The HTTP status code of the error, if the API responded with one
+	#[cfg(feature = "http_client_core")]
+	#[must_use]
+	pub const fn status(&self) -> Option<http::StatusCode> {
+		match self {
+			Self::Api { status, .. } => Some(*status),
+			_ => None,
+		}
+	}
+
+	/// If the API rejected the authentication of the request
+	#[cfg(feature = "http_client_core")]
+	#[must_use]
+	pub fn is_unauthorized(&self) -> bool {
+		self.status() == Some(http::StatusCode::UNAUTHORIZED)
+	}
+
+	/// If the API didn't find what was requested
+	#[cfg(feature = "http_client_core")]
+	#[must_use]
+	pub fn is_not_found(&self) -> bool {
+		self.status() == Some(http::StatusCode::NOT_FOUND)
+	}
 }
 
-#[cfg(feature = "http_client")]
-impl From<reqwest::Error> for ApiError {
-	fn from(err: reqwest::Error) -> Self { Self::Http(err) }
+impl std::fmt::Display for ApiError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Serde(err) => {
+				write!(f, "An error happened with serialization: {err}")
+			}
+			#[cfg(feature = "http_client_core")]
+			Self::Http(err) => write!(f, "An error happened with the request: {err}"),
+			#[cfg(feature = "ws_client")]
+			Self::WebSocket(err) => {
+				write!(f, "An error happened with the WS connection: {err}")
+			}
+			#[cfg(feature = "ws_client")]
+			Self::Lagged(skipped) => {
+				write!(f, "The WS listener fell behind and skipped {skipped} messages")
+			}
+			#[cfg(feature = "http_client_core")]
+			Self::Api { status, message } => {
+				write!(f, "The API responded with {status}: {message}")
+			}
+			#[cfg(feature = "http_client_core")]
+			Self::InvalidCredentials(message) => {
+				write!(f, "The login credentials were rejected: {message}")
+			}
+			#[cfg(any(feature = "http_client_core", feature = "ws_client"))]
+			Self::CredentialStore(err) => {
+				write!(f, "An error happened with the credential store: {err}")
+			}
+			#[cfg(feature = "ws_client")]
+			Self::Rejected(response) => {
+				write!(f, "The API rejected the WS request: {}", response.message)
+			}
+			#[cfg(feature = "ws_client")]
+			Self::Timeout => write!(f, "The API didn't respond to the WS request"),
+			#[cfg(feature = "blocking")]
+			Self::Runtime(err) => write!(f, "Creating the runtime failed: {err}"),
+		}
+	}
 }
 
-#[cfg(feature = "http_client")]
-impl From<racal::reqwest::ApiError> for ApiError {
-	fn from(err: racal::reqwest::ApiError) -> Self {
-		match err {
-			racal::reqwest::ApiError::Reqwest(e) => Self::Http(e),
-			racal::reqwest::ApiError::Serde(e) => Self::Serde(e),
+impl std::error::Error for ApiError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Self::Serde(err) => Some(err),
+			#[cfg(feature = "http_client_core")]
+			Self::Http(err) => Some(err),
+			#[cfg(feature = "ws_client")]
+			Self::WebSocket(err) => Some(err.as_ref()),
+			#[cfg(any(feature = "http_client_core", feature = "ws_client"))]
+			Self::CredentialStore(err) => Some(err),
+			#[cfg(feature = "blocking")]
+			Self::Runtime(err) => Some(err),
+			#[cfg(any(feature = "http_client_core", feature = "ws_client"))]
+			_ => None,
 		}
 	}
 }
 
-#[cfg(feature = "ws_client")]
+impl From<serde_json::Error> for ApiError {
+	fn from(err: serde_json::Error) -> Self { Self::Serde(err) }
+}
+
+#[cfg(feature = "http_client_core")]
+impl From<HttpError> for ApiError {
+	fn from(err: HttpError) -> Self { Self::Http(err) }
+}
+
+#[cfg(all(feature = "ws_client", not(target_arch = "wasm32")))]
 impl From<ezsockets::Error> for ApiError {
 	fn from(err: ezsockets::Error) -> Self { Self::WebSocket(err) }
 }
 
-#[cfg(feature = "http_client")]
-type NormalRateLimiter =
-	RateLimiter<NotKeyed, InMemoryState, DefaultClock, NoOpMiddleware>;
-
-#[cfg(feature = "http_client")]
-#[must_use]
-fn http_rate_limiter() -> NormalRateLimiter {
-	// ~5 seconds per request sustained over one minute, allowing up to a request
-	// per second in bursts.
-	RateLimiter::direct(
-		Quota::per_minute(NonZeroU32::try_from(12).unwrap())
-			.allow_burst(NonZeroU32::try_from(5).unwrap()),
-	)
+/// Parses headers into a header map
+#[cfg(feature = "http_client_core")]
+fn header_map(
+	what: &str,
+	headers: Result<Vec<(HeaderName, HeaderValue)>, InvalidHeaderValue>,
+) -> Result<HeaderMap, serde_json::Error> {
+	use serde::ser::Error;
+
+	Ok(HeaderMap::from_iter(headers.map_err(|e| {
+		serde_json::Error::custom(
+			format!("Couldn't parse {what} into headers: ") + &e.to_string(),
+		)
+	})?))
+}
+
+/// Builds the request of a query, pointed to the configured API and with the
+/// headers of the client
+#[cfg(feature = "http_client_core")]
+fn build_request<ApiClientType, ReturnType, FromState, QueryableType>(
+	api_client: &ApiClientType, queryable: &QueryableType,
+) -> Result<HttpRequest, ApiError>
+where
+	ApiClientType: QueryContext,
+	ReturnType: serde::de::DeserializeOwned,
+	FromState: racal::FromApiState<ApiClientType::State>,
+	QueryableType: racal::Queryable<FromState, ReturnType>,
+{
+	use serde::ser::Error;
+
+	let state = FromState::from_state(api_client.state());
+	let method = match queryable.method(state) {
+		racal::RequestMethod::Get => http::Method::GET,
+		racal::RequestMethod::Head => http::Method::HEAD,
+		racal::RequestMethod::Patch => http::Method::PATCH,
+		racal::RequestMethod::Post => http::Method::POST,
+		racal::RequestMethod::Put => http::Method::PUT,
+		racal::RequestMethod::Delete => http::Method::DELETE,
+	};
+	let url = api_client.config().rebase_url(&queryable.url(state));
+	let mut request = http::Request::builder().method(method).uri(url);
+	if let Some(headers) = request.headers_mut() {
+		headers.extend(api_client.headers().clone());
+		headers.extend(api_client.auth_headers()?);
+	}
+	let body = match queryable.body(state) {
+		Some(body) => {
+			request = request.header(http::header::CONTENT_TYPE, "application/json");
+			body?
+		}
+		None => Vec::new(),
+	};
+	Ok(request.body(body).map_err(|e| {
+		serde_json::Error::custom(
+			"Couldn't build the request: ".to_string() + &e.to_string(),
+		)
+	})?)
+}
+
+/// How an authenticated client logs in again when its credentials stop working
+#[cfg(feature = "http_client_core")]
+#[derive(Debug, Clone)]
+struct Reauthentication {
+	/// What to login with instead of the current credentials
+	login: Option<crate::query::LoginCredentials>,
+}
+
+/// What the shared query logic needs from the API clients
+#[cfg(feature = "http_client_core")]
+trait QueryContext: Sync {
+	/// The state that the queries are built from
+	type State;
+
+	fn state(&self) -> &Self::State;
+	fn config(&self) -> &ApiConfiguration;
+	fn backend(&self) -> &dyn HttpBackend;
+	fn rate_limiters(&self) -> &RateLimiters;
+	/// The headers of the configuration
+	fn headers(&self) -> &HeaderMap;
+	/// The headers of the current credentials, if any
+	fn auth_headers(&self) -> Result<HeaderMap, serde_json::Error>;
+	fn cache(&self) -> Option<&ResponseCache>;
+	/// Separates the cached responses of different users
+	fn cache_scope(&self) -> String;
+	fn middleware(&self) -> &middleware::Chain;
+}
+
+/// Sends a query, letting the middleware know if it fails
+#[cfg(feature = "http_client_core")]
+async fn query<ApiClientType, ReturnType, FromState, QueryableType>(
+	api_client: &ApiClientType, queryable: &QueryableType,
+) -> Result<ReturnType, ApiError>
+where
+	ApiClientType: QueryContext,
+	ReturnType: serde::de::DeserializeOwned,
+	FromState: racal::FromApiState<ApiClientType::State>,
+	QueryableType: racal::Queryable<FromState, ReturnType> + Send + Sync,
+{
+	let result = send_query(api_client, queryable).await;
+	if let Err(err) = &result {
+		api_client.middleware().on_error(err);
+	}
+	result
+}
+
+/// Sends a query, turning error responses into [`ApiError::Api`], retrying
+/// idempotent queries as per the [`RetryPolicy`] and caching the responses of
+/// `GET` queries if there's a [`ResponseCache`]
+#[cfg(feature = "http_client_core")]
+async fn send_query<ApiClientType, ReturnType, FromState, QueryableType>(
+	api_client: &ApiClientType, queryable: &QueryableType,
+) -> Result<ReturnType, ApiError>
+where
+	ApiClientType: QueryContext,
+	ReturnType: serde::de::DeserializeOwned,
+	FromState: racal::FromApiState<ApiClientType::State>,
+	QueryableType: racal::Queryable<FromState, ReturnType> + Send + Sync,
+{
+	let config = api_client.config();
+	let scope = &api_client.cache_scope();
+	let method = queryable.method(FromState::from_state(api_client.state()));
+	let idempotent =
+		matches!(method, racal::RequestMethod::Get | racal::RequestMethod::Head);
+
+	let cache = api_client
+		.cache()
+		.filter(|cache| {
+			matches!(method, racal::RequestMethod::Get)
+				&& cache.caches::<QueryableType>()
+		})
+		.map(|cache| {
+			let url = queryable.url(FromState::from_state(api_client.state()));
+			(cache, config.rebase_url(&url))
+		});
+	if let Some((cache, url)) = &cache {
+		if let Some(body) = cache.get(scope, url) {
+			return Ok(queryable.deserialize(&body)?);
+		}
+	}
+
+	let mut attempt = 0;
+	loop {
+		attempt += 1;
+		let mut request = build_request(api_client, queryable)?;
+		let route =
+			Route::of(&queryable.url(FromState::from_state(api_client.state())));
+		api_client.rate_limiters().until_ready(route).await;
+		api_client.middleware().before_request(&mut request).await;
+		let (method, url) = (request.method().clone(), request.uri().clone());
+		let sent_at = rt::Instant::now();
+		let delay = match api_client.backend().execute(request).await {
+			Ok(response) => {
+				let status = response.status();
+				let (parts, body) = response.into_parts();
+				api_client.middleware().after_response(&middleware::ResponseInfo {
+					method: &method,
+					url: &url,
+					status,
+					headers: &parts.headers,
+					body: &body,
+					elapsed: sent_at.elapsed(),
+				});
+				if status.is_success() {
+					let response = queryable.deserialize(&body)?;
+					if let Some((cache, url)) = &cache {
+						cache.insert::<QueryableType>(scope, url, body.into());
+					}
+					return Ok(response);
+				}
+				let err = ApiError::from_response(status, &body);
+				if !idempotent || !retry::is_retryable_status(status) {
+					return Err(err);
+				}
+				let retry_after = retry::retry_after(&parts.headers);
+				config.retry.next_delay(attempt, retry_after).ok_or(err)?
+			}
+			Err(err) => {
+				if !idempotent || !err.is_transient() {
+					return Err(err.into());
+				}
+				config.retry.next_delay(attempt, None).ok_or(err)?
+			}
+		};
+		rt::sleep(delay).await;
+	}
 }
 
 /// The main API client without authentication
-#[cfg(feature = "http_client")]
+#[cfg(feature = "http_client_core")]
 pub struct UnauthenticatedCVR {
 	config: ApiConfiguration,
-	http: Client,
-	http_rate_limiter: NormalRateLimiter,
+	/// The headers of the configuration
+	headers: HeaderMap,
+	backend: Arc<dyn HttpBackend>,
+	rate_limiters: Arc<RateLimiters>,
+	cache: Option<ResponseCache>,
+	middleware: middleware::Chain,
+	credential_store: Option<StoredAccount>,
 }
 
-#[cfg(feature = "http_client")]
-#[async_trait::async_trait]
-impl racal::reqwest::ApiClient<NoAuthentication> for UnauthenticatedCVR {
+#[cfg(feature = "http_client_core")]
+impl QueryContext for UnauthenticatedCVR {
+	type State = NoAuthentication;
+
 	fn state(&self) -> &NoAuthentication { &NoAuthentication {} }
 
-	fn client(&self) -> &reqwest::Client { &self.http }
+	fn config(&self) -> &ApiConfiguration { &self.config }
+
+	fn backend(&self) -> &dyn HttpBackend { self.backend.as_ref() }
 
-	async fn before_request(
-		&self, req: RequestBuilder,
-	) -> Result<RequestBuilder, racal::reqwest::ApiError> {
-		self.http_rate_limiter.until_ready().await;
-		Ok(req)
+	fn rate_limiters(&self) -> &RateLimiters { &self.rate_limiters }
+
+	fn headers(&self) -> &HeaderMap { &self.headers }
+
+	fn auth_headers(&self) -> Result<HeaderMap, serde_json::Error> {
+		Ok(HeaderMap::new())
 	}
+
+	fn cache(&self) -> Option<&ResponseCache> { self.cache.as_ref() }
+
+	fn cache_scope(&self) -> String { String::new() }
+
+	fn middleware(&self) -> &middleware::Chain { &self.middleware }
 }
 
 /// The main API client with authentication
 pub struct AuthenticatedCVR {
 	config: ApiConfiguration,
+	/// The credentials the client was created with, which the queries see
 	auth: SavedLoginCredentials,
-	#[cfg(feature = "http_client")]
-	http: Client,
-	#[cfg(feature = "http_client")]
-	http_rate_limiter: NormalRateLimiter,
+	/// The credentials that are currently used, which change when
+	/// re-authenticating
+	credentials: Arc<std::sync::RwLock<SavedLoginCredentials>>,
+	#[cfg(feature = "http_client_core")]
+	reauthentication: Option<Reauthentication>,
+	#[cfg(feature = "http_client_core")]
+	reauthentication_lock: tokio::sync::Mutex<()>,
+	#[cfg(feature = "http_client_core")]
+	headers: HeaderMap,
+	#[cfg(feature = "http_client_core")]
+	backend: Arc<dyn HttpBackend>,
+	#[cfg(any(feature = "http_client_core", feature = "ws_client"))]
+	rate_limiters: Arc<RateLimiters>,
+	#[cfg(feature = "http_client_core")]
+	cache: Option<ResponseCache>,
+	#[cfg(any(feature = "http_client_core", feature = "ws_client"))]
+	middleware: middleware::Chain,
+	#[cfg(any(feature = "http_client_core", feature = "ws_client"))]
+	credential_store: Option<StoredAccount>,
 	#[cfg(feature = "ws_client")]
 	ws: tokio::sync::RwLock<Option<ws::Client>>,
+	#[cfg(feature = "ws_client")]
+	ws_status: ws::Status,
+	#[cfg(feature = "ws_client")]
+	ws_transport: Arc<dyn WsTransport>,
 }
 
-#[cfg(feature = "http_client")]
-#[async_trait::async_trait]
-impl racal::reqwest::ApiClient<SavedLoginCredentials> for AuthenticatedCVR {
+#[cfg(feature = "http_client_core")]
+impl QueryContext for AuthenticatedCVR {
+	type State = SavedLoginCredentials;
+
 	fn state(&self) -> &SavedLoginCredentials { &self.auth }
 
-	fn client(&self) -> &reqwest::Client { &self.http }
+	fn config(&self) -> &ApiConfiguration { &self.config }
+
+	fn backend(&self) -> &dyn HttpBackend { self.backend.as_ref() }
 
-	async fn before_request(
-		&self, req: RequestBuilder,
-	) -> Result<RequestBuilder, racal::reqwest::ApiError> {
-		self.http_rate_limiter.until_ready().await;
-		Ok(req)
+	fn rate_limiters(&self) -> &RateLimiters { &self.rate_limiters }
+
+	fn headers(&self) -> &HeaderMap { &self.headers }
+
+	fn auth_headers(&self) -> Result<HeaderMap, serde_json::Error> {
+		header_map("auth", self.credentials().to_headers())
 	}
+
+	fn cache(&self) -> Option<&ResponseCache> { self.cache.as_ref() }
+
+	fn cache_scope(&self) -> String { self.credentials().username }
+
+	fn middleware(&self) -> &middleware::Chain { &self.middleware }
 }
 
 impl AuthenticatedCVR {
-	/// Creates an API client
-	#[cfg(feature = "http_client")]
-	fn http_client(
-		config: &ApiConfiguration, auth: &SavedLoginCredentials,
-	) -> Result<Client, ApiError> {
-		use serde::ser::Error;
-
-		let builder = Client::builder();
-		let mut headers: Vec<(HeaderName, HeaderValue)> =
-			config.to_headers().map_err(|e| {
-				serde_json::Error::custom(
-					"Couldn't parse config into headers: ".to_string() + &e.to_string(),
-				)
-			})?;
-		headers.append(&mut auth.to_headers().map_err(|e| {
-			serde_json::Error::custom(
-				"Couldn't parse auth into headers: ".to_string() + &e.to_string(),
-			)
-		})?);
+	/// Sends a query to the CVR API
+	///
+	/// Error responses of the API are turned into [`ApiError::Api`], failed
+	/// idempotent queries are retried as per the configured [`RetryPolicy`],
+	/// and responses are cached if there's a [`ResponseCache`].
+	///
+	/// If re-authentication is enabled and the API rejects the credentials,
+	/// the client logs in again and replays the query once.
+	///
+	/// # Errors
+	///
+	/// If the request fails, the API responds with an error, or the response
+	/// can't be deserialized.
+	#[cfg(feature = "http_client_core")]
+	pub async fn query<ReturnType, FromState, QueryableType>(
+		&self, queryable: QueryableType,
+	) -> Result<ReturnType, ApiError>
+	where
+		ReturnType: serde::de::DeserializeOwned,
+		FromState: racal::FromApiState<SavedLoginCredentials>,
+		QueryableType: racal::Queryable<FromState, ReturnType> + Send + Sync,
+	{
+		let credentials = self.credentials();
+		// The middleware only hears of the rejection if the query isn't replayed
+		let result = match send_query(self, &queryable).await {
+			Err(err) if err.is_unauthorized() && self.reauthentication.is_some() => {
+				match self.reauthenticate(&credentials).await {
+					Ok(()) => send_query(self, &queryable).await,
+					Err(err) => Err(err),
+				}
+			}
+			result => result,
+		};
+		if let Err(err) = &result {
+			self.middleware.on_error(err);
+		}
+		result
+	}
+
+	/// The authentication that the client was created with, which the queries
+	/// are built from
+	///
+	/// This isn't updated when the client re-authenticates, see
+	/// [`credentials`](Self::credentials) for the ones currently in use.
+	#[must_use]
+	pub const fn state(&self) -> &SavedLoginCredentials { &self.auth }
+
+	/// The credentials that the client is currently using
+	///
+	/// These differ from the ones that the client was created with if it has
+	/// re-authenticated since.
+	#[must_use]
+	pub fn credentials(&self) -> SavedLoginCredentials {
+		self
+			.credentials
+			.read()
+			.unwrap_or_else(std::sync::PoisonError::into_inner)
+			.clone()
+	}
+
+	/// Makes the client login again when the API rejects its credentials,
+	/// replaying the rejected query once afterwards
+	///
+	/// The client logs in with the login details if they're provided, and
+	/// otherwise with the saved credentials from its credential store, or the
+	/// rejected ones if it doesn't have a store. The new credentials are saved
+	/// if the client has a credential store, and used for both HTTP and WS.
+	/// If logging in fails, the query isn't replayed and fails with the error
+	/// of logging in, [`ApiError::InvalidCredentials`] if it was rejected.
+	#[cfg(feature = "http_client_core")]
+	#[must_use]
+	pub fn with_reauthentication(
+		mut self, login: Option<crate::query::LoginCredentials>,
+	) -> Self {
+		self.reauthentication = Some(Reauthentication { login });
+		self
+	}
+
+	/// Logs in again, unless someone else already did so after the credentials
+	/// were rejected
+	#[cfg(feature = "http_client_core")]
+	async fn reauthenticate(
+		&self, rejected: &SavedLoginCredentials,
+	) -> Result<(), ApiError> {
+		let Some(reauthentication) = &self.reauthentication else {
+			return Ok(());
+		};
+		let _guard = self.reauthentication_lock.lock().await;
+		if self.credentials() != *rejected {
+			return Ok(());
+		}
+
+		let auth = match reauthentication.login.clone() {
+			Some(login) => AuthType::LoginProfile(login),
+			None => AuthType::LoginCredentials(self.stored_credentials(rejected)?),
+		};
+		let unauthenticated = UnauthenticatedCVR {
+			config: self.config.clone(),
+			headers: self.headers.clone(),
+			backend: self.backend.clone(),
+			rate_limiters: self.rate_limiters.clone(),
+			cache: None,
+			middleware: self.middleware.clone(),
+			credential_store: self.credential_store.clone(),
+		};
+		let credentials =
+			SavedLoginCredentials::from(&unauthenticated.authenticate(auth).await?);
+		*self
+			.credentials
+			.write()
+			.unwrap_or_else(std::sync::PoisonError::into_inner) = credentials;
 
-		let headers = HeaderMap::from_iter(headers);
+		#[cfg(feature = "ws_client")]
+		if let Some(ws_client) = &*self.ws.read().await {
+			ws_client.reconnect();
+		}
 
-		Ok(builder.default_headers(headers).build()?)
+		Ok(())
+	}
+
+	/// The credentials to login with again, preferring the ones in the store in
+	/// case they were renewed elsewhere, like by another process that logged in
+	#[cfg(feature = "http_client_core")]
+	fn stored_credentials(
+		&self, rejected: &SavedLoginCredentials,
+	) -> Result<SavedLoginCredentials, ApiError> {
+		let stored = match &self.credential_store {
+			Some(stored) => stored.load().map_err(ApiError::CredentialStore)?,
+			None => None,
+		};
+		Ok(stored.unwrap_or_else(|| rejected.clone()))
 	}
 
 	/// Removes authentication to the API client
@@ -239,132 +825,304 @@ impl AuthenticatedCVR {
 	/// If there's an internal programming error, aka should never panic.ails
 	pub fn new(
 		config: ApiConfiguration, auth: impl Into<SavedLoginCredentials> + Send,
+	) -> Result<Self, ApiError> {
+		let rate_limiters = Arc::new(RateLimiters::new(&config.rate_limits));
+		Self::with_rate_limiters(config, auth, rate_limiters)
+	}
+
+	/// Creates an API client with the credentials of an account in the store
+	///
+	/// The client keeps the store, so that the credentials stay saved if they
+	/// change.
+	///
+	/// # Errors
+	///
+	/// If loading the credentials fails or there aren't any for the account,
+	/// or if deserializing user agent or authentication fails.
+	#[cfg(any(feature = "http_client_core", feature = "ws_client"))]
+	pub fn from_store(
+		config: ApiConfiguration, store: Arc<dyn CredentialStore>,
+		account: impl Into<String>,
+	) -> Result<Self, ApiError> {
+		let account = account.into();
+		let auth = store
+			.load(&account)
+			.map_err(ApiError::CredentialStore)?
+			.ok_or_else(|| {
+				ApiError::CredentialStore(std::io::Error::new(
+					std::io::ErrorKind::NotFound,
+					format!("No credentials stored for the account {account}"),
+				))
+			})?;
+		let mut client = Self::new(config, auth)?;
+		client.credential_store = Some(StoredAccount { store, account });
+		Ok(client)
+	}
+
+	/// Creates an API client that shares its rate limits with other clients
+	///
+	/// # Errors
+	///
+	/// If deserializing user agent or authentication fails.
+	#[cfg(any(feature = "http_client_core", feature = "ws_client"))]
+	pub fn with_rate_limiter(
+		config: ApiConfiguration, auth: impl Into<SavedLoginCredentials> + Send,
+		rate_limiter: &SharedRateLimiter,
+	) -> Result<Self, ApiError> {
+		let rate_limiters = rate_limiter.for_url(&config.http_url);
+		Self::with_rate_limiters(config, auth, rate_limiters)
+	}
+
+	/// Caches the responses of read-only queries
+	///
+	/// The cache is kept when the client is downgraded, with the responses
+	/// being cached separately for each user.
+	#[cfg(feature = "http_client_core")]
+	#[must_use]
+	pub fn with_cache(mut self, cache: ResponseCache) -> Self {
+		self.cache = Some(cache);
+		self
+	}
+
+	/// Adds middleware to the API client, to be run after the already added
+	/// ones
+	///
+	/// The middleware is kept when the client is downgraded. An already open
+	/// WS connection doesn't see the newly added middleware until it's reopened.
+	#[cfg(any(feature = "http_client_core", feature = "ws_client"))]
+	#[must_use]
+	pub fn with_middleware(
+		mut self, middleware: impl Middleware + 'static,
+	) -> Self {
+		self.middleware.push(Arc::new(middleware));
+		self
+	}
+
+	fn with_rate_limiters(
+		config: ApiConfiguration, auth: impl Into<SavedLoginCredentials> + Send,
+		rate_limiters: Arc<RateLimiters>,
 	) -> Result<Self, ApiError> {
 		let auth = auth.into();
+		#[cfg(feature = "http_client_core")]
+		header_map("auth", auth.to_headers())?;
 		Ok(Self {
-			#[cfg(feature = "http_client")]
-			http: Self::http_client(&config, &auth)?,
-			#[cfg(feature = "http_client")]
-			http_rate_limiter: http_rate_limiter(),
+			#[cfg(feature = "http_client_core")]
+			headers: header_map("config", config.to_headers())?,
+			#[cfg(feature = "http_client_core")]
+			backend: backend::default_backend()?,
+			credentials: Arc::new(std::sync::RwLock::new(auth.clone())),
+			#[cfg(feature = "http_client_core")]
+			reauthentication: None,
+			#[cfg(feature = "http_client_core")]
+			reauthentication_lock: tokio::sync::Mutex::new(()),
+			rate_limiters,
+			#[cfg(feature = "http_client_core")]
+			cache: None,
+			middleware: middleware::Chain::default(),
+			credential_store: None,
 			#[cfg(feature = "ws_client")]
 			ws: tokio::sync::RwLock::new(None),
+			#[cfg(feature = "ws_client")]
+			ws_status: ws::Status::default(),
+			#[cfg(feature = "ws_client")]
+			ws_transport: transport::default_transport(),
 			auth,
 			config,
 		})
 	}
 
-	/// Opens the WebSocket connection if it wasn't already open
+	/// Sends the HTTP requests with the backend instead of the default one
 	///
-	/// # Errors
+	/// The backend is kept when the client is downgraded.
+	#[cfg(feature = "http_client_core")]
+	#[must_use]
+	pub fn with_http_backend(mut self, backend: Arc<dyn HttpBackend>) -> Self {
+		self.backend = backend;
+		self
+	}
+
+	/// Uses the transport for WS connections instead of the default one
 	///
-	/// If opening the WS connection fails
+	/// Only affects connections that are opened afterwards.
 	#[cfg(feature = "ws_client")]
-	pub async fn ws_connect(&self) -> Result<(), ApiError> {
-		{
-			let lock = self.ws.read().await;
-			if lock.is_some() {
-				return Ok(());
-			}
-		}
+	#[must_use]
+	pub fn with_ws_transport(mut self, transport: Arc<dyn WsTransport>) -> Self {
+		self.ws_transport = transport;
+		self
+	}
 
-		#[cfg(feature = "http_client")]
-		self.http_rate_limiter.until_ready().await;
-		let client = ws::Client::new(&self.config, &self.auth).await?;
-		{
-			let mut lock = self.ws.write().await;
-			*lock = Some(client);
+	#[cfg(feature = "ws_client")]
+	fn ws_connector(&self) -> ws::Connector {
+		ws::Connector {
+			config: self.config.clone(),
+			credentials: self.credentials.clone(),
+			rate_limiters: self.rate_limiters.clone(),
+			middleware: self.middleware.clone(),
+			status: self.ws_status.clone(),
+			transport: self.ws_transport.clone(),
 		}
+	}
 
-		Ok(())
+	/// Opens the WebSocket connection if it wasn't already open, or if the
+	/// client gave up on reconnecting it
+	///
+	/// # Errors
+	///
+	/// If opening the WS connection fails
+	#[cfg(feature = "ws_client")]
+	pub async fn ws_connect(&self) -> Result<(), ApiError> {
+		self.with_ws(|_| ()).await
 	}
 
 	/// Closes the WebSocket connection if it is open
+	///
+	/// This also stops the configured keepalive, until the connection is opened
+	/// again.
 	#[cfg(feature = "ws_client")]
 	pub async fn ws_disconnect(&self) {
 		{
 			let mut lock = self.ws.write().await;
 			*lock = None;
 		}
+		self.ws_status.set(ConnectionState::Closed);
 	}
 
-	/// If the WS client is connected
+	/// If the WS connection is currently open
 	#[cfg(feature = "ws_client")]
+	// Async so that existing callers don't break
+	#[allow(clippy::unused_async)]
 	pub async fn ws_is_connected(&self) -> bool {
-		let lock = self.ws.read().await;
-		lock.is_some()
+		self.ws_status.get() == ConnectionState::Connected
+	}
+
+	/// The current state of the WS connection
+	#[cfg(feature = "ws_client")]
+	#[must_use]
+	pub fn ws_state(&self) -> ConnectionState { self.ws_status.get() }
+
+	/// A stream of the current state of the WS connection, followed by every
+	/// change to it
+	///
+	/// States that change quickly in succession may be skipped over, so only
+	/// the latest one is guaranteed to be seen.
+	#[cfg(feature = "ws_client")]
+	pub fn ws_state_changes(
+		&self,
+	) -> impl tokio_stream::Stream<Item = ConnectionState> + Send + Unpin + 'static
+	{
+		self.ws_status.changes()
+	}
+
+	/// Why the WS connection was last lost, even if it has been re-established
+	/// since
+	#[cfg(feature = "ws_client")]
+	#[must_use]
+	pub fn ws_last_error(&self) -> Option<String> { self.ws_status.last_error() }
+
+	/// Runs the function with the WS client, opening the connection first if it
+	/// wasn't already open, or if the client gave up on reconnecting
+	#[cfg(feature = "ws_client")]
+	async fn with_ws<T>(
+		&self, f: impl FnOnce(&ws::Client) -> T + Send,
+	) -> Result<T, ApiError> {
+		{
+			let lock = self.ws.read().await;
+			if let Some(ws_client) =
+				lock.as_ref().filter(|ws_client| !ws_client.is_finished())
+			{
+				return Ok(f(ws_client));
+			}
+		}
+
+		let client = ws::Client::new(self.ws_connector()).await?;
+		let mut lock = self.ws.write().await;
+		Ok(f(lock.insert(client)))
 	}
 
-	// Clippy seems to be wrong, or at least haven't been able to figure out how
-	// this could be cleaned up more...
-	#[allow(clippy::significant_drop_tightening)]
 	/// Sends a WS message to the CVR API.
 	///
+	/// Messages sent while the connection is being re-established are queued
+	/// as per [`ApiConfiguration::ws_queue`], and sent once it's back.
+	///
 	/// # Errors
 	///
 	/// If something with the request failed,
 	/// or if the WS connection wasn't already open and creating it failed.
-	///
-	/// # Panics
-	///
-	/// If there's an internal programming error, aka should never panic.
 	#[cfg(feature = "ws_client")]
 	pub async fn send(
 		&self,
 		requestable: impl crate::query::Requestable + serde::ser::Serialize + Send,
 	) -> Result<(), ApiError> {
-		{
-			let lock = self.ws.read().await;
-			if let Some(ws_client) = &*lock {
-				return ws_client.send(requestable);
-			}
-		}
+		self.with_ws(|ws_client| ws_client.send(requestable)).await?
+	}
 
-		#[cfg(feature = "http_client")]
-		self.http_rate_limiter.until_ready().await;
-		let client = ws::Client::new(&self.config, &self.auth).await?;
-		let mut lock = self.ws.write().await;
-		*lock = Some(client);
-		let lock = lock.downgrade();
-		(*lock)
-			.as_ref()
-			.expect("client should exist as lock was never dropped")
-			.send(requestable)
+	/// Sends a WS message to the CVR API, and waits for the API to respond to it
+	///
+	/// Resolves to the update that confirms the request, for example the friend
+	/// requests without the accepted one after accepting it. Waiting is done on
+	/// a listener of its own, so other listeners still get the update too.
+	///
+	/// Some requests, like sending friend requests or invites, don't get an
+	/// update that could be told apart from ones caused by other requests.
+	/// Those resolve to [`WsOutcome::Unconfirmed`] if they aren't rejected
+	/// within the timeout.
+	///
+	/// ```no_run
+	/// # async fn example(
+	/// # 	api_client: chilloutvr::api_client::AuthenticatedCVR,
+	/// # 	id: chilloutvr::id::Invite,
+	/// # ) -> Result<(), chilloutvr::api_client::ApiError> {
+	/// use std::time::Duration;
+	///
+	/// use chilloutvr::query::AcceptFriendRequest;
+	///
+	/// api_client
+	/// 	.send_and_confirm(AcceptFriendRequest { id }, Duration::from_secs(10))
+	/// 	.await?;
+	/// # Ok(())
+	/// # }
+	/// ```
+	///
+	/// # Errors
+	///
+	/// If sending the request failed, the API responded with a popup
+	/// ([`ApiError::Rejected`]), or didn't confirm the request within the
+	/// timeout ([`ApiError::Timeout`]).
+	#[cfg(feature = "ws_client")]
+	pub async fn send_and_confirm(
+		&self,
+		requestable: impl crate::query::Confirmable
+		+ serde::ser::Serialize
+		+ Clone
+		+ Send
+		+ Sync,
+		timeout: std::time::Duration,
+	) -> Result<WsOutcome, ApiError> {
+		// Subscribed before sending, so that the response can't be missed
+		let mut listener = self
+			.with_ws(|ws_client| {
+				let listener = ws_client.subscribe();
+				ws_client.send(requestable.clone()).map(|()| listener)
+			})
+			.await??;
+		match rt::timeout(timeout, listener.confirmation(&requestable)).await {
+			Some(response) => response.map(WsOutcome::Confirmed),
+			None if requestable.is_confirmable() => Err(ApiError::Timeout),
+			None => Ok(WsOutcome::Unconfirmed),
+		}
 	}
 
-	// Clippy seems to be wrong, or at least haven't been able to figure out how
-	// this could be cleaned up more...
-	#[allow(clippy::significant_drop_tightening)]
 	/// Listens to events from the WS connection
 	///
+	/// Each listener receives every message, independently of other listeners.
+	///
 	/// # Errors
 	///
 	/// If creating the client fails,
 	/// or if the WS connection wasn't already open and creating it failed.
-	///
-	/// # Panics
-	///
-	/// If there's an internal programming error, aka should never panic.
 	#[cfg(feature = "ws_client")]
-	pub async fn listen(&self) -> Result<ws::ReceiverContainer, ApiError> {
-		{
-			let lock = self.ws.read().await;
-			if let Some(ws_client) = &*lock {
-				return Ok(ws_client.listen());
-			}
-		}
-
-		#[cfg(feature = "http_client")]
-		self.http_rate_limiter.until_ready().await;
-		let client = ws::Client::new(&self.config, &self.auth).await?;
-		let mut lock = self.ws.write().await;
-		*lock = Some(client);
-		let lock = lock.downgrade();
-		Ok(
-			(*lock)
-				.as_ref()
-				.expect("client should exist as lock was never dropped")
-				.listen(),
-		)
+	pub async fn listen(&self) -> Result<WsListener, ApiError> {
+		self.with_ws(ws::Client::listen).await
 	}
 
 	/// Removes the authentication from the API client
@@ -372,44 +1130,129 @@ impl AuthenticatedCVR {
 	/// # Errors
 	///
 	/// If locking fails
+	#[cfg_attr(not(feature = "ws_client"), allow(clippy::unused_async))]
 	pub async fn downgrade(self) -> Result<UnauthenticatedCVR, ApiError> {
-		{
-			let mut lock = self.ws.write().await;
-			*lock = None;
-		}
-		let http = UnauthenticatedCVR::http_client(&self.config.user_agent)?;
+		#[cfg(feature = "ws_client")]
+		self.ws_disconnect().await;
 		Ok(UnauthenticatedCVR {
 			config: self.config,
-			http,
-			http_rate_limiter: self.http_rate_limiter,
+			headers: self.headers,
+			backend: self.backend,
+			rate_limiters: self.rate_limiters,
+			cache: self.cache,
+			middleware: self.middleware,
+			credential_store: self.credential_store,
 		})
 	}
 }
 
-#[cfg(feature = "http_client")]
+#[cfg(feature = "http_client_core")]
 impl UnauthenticatedCVR {
-	/// Creates an unauthenticated API client
-	fn http_client(user_agent: &str) -> Result<Client, ApiError> {
-		Ok(Client::builder().user_agent(user_agent).build()?)
+	/// Sends a query to the CVR API
+	///
+	/// Error responses of the API are turned into [`ApiError::Api`], failed
+	/// idempotent queries are retried as per the configured [`RetryPolicy`],
+	/// and responses are cached if there's a [`ResponseCache`].
+	///
+	/// # Errors
+	///
+	/// If the request fails, the API responds with an error, or the response
+	/// can't be deserialized.
+	pub async fn query<ReturnType, FromState, QueryableType>(
+		&self, queryable: QueryableType,
+	) -> Result<ReturnType, ApiError>
+	where
+		ReturnType: serde::de::DeserializeOwned,
+		FromState: racal::FromApiState<NoAuthentication>,
+		QueryableType: racal::Queryable<FromState, ReturnType> + Send + Sync,
+	{
+		query(self, &queryable).await
 	}
 
 	/// Adds authentication to the API client
 	///
+	/// The credentials are saved if the client has a credential store.
+	///
 	/// # Errors
 	///
-	/// If deserializing user agent or authentication fails.
+	/// If deserializing user agent or authentication fails, or if saving the
+	/// credentials fails.
 	pub fn upgrade(
 		self, auth: impl Into<SavedLoginCredentials> + Send,
 	) -> Result<AuthenticatedCVR, ApiError> {
 		let auth = auth.into();
-		Ok(AuthenticatedCVR {
-			http: AuthenticatedCVR::http_client(&self.config, &auth)?,
-			http_rate_limiter: self.http_rate_limiter,
-			#[cfg(feature = "ws_client")]
-			ws: tokio::sync::RwLock::new(None),
+		self.save_credentials(&auth)?;
+		self.authenticated(auth)
+	}
+
+	/// Saves the credentials of future logins & upgrades to the account in the
+	/// store
+	#[must_use]
+	pub fn with_credential_store(
+		mut self, store: Arc<dyn CredentialStore>, account: impl Into<String>,
+	) -> Self {
+		self.credential_store =
+			Some(StoredAccount { store, account: account.into() });
+		self
+	}
+
+	fn save_credentials(
+		&self, auth: &SavedLoginCredentials,
+	) -> Result<(), ApiError> {
+		if let Some(credential_store) = &self.credential_store {
+			credential_store.save(auth).map_err(ApiError::CredentialStore)?;
+		}
+		Ok(())
+	}
+
+	/// Logs in, returning an authenticated API client along with the details of
+	/// the user that was logged in as
+	///
+	/// The authenticated client shares the rate limits, cache, middleware and
+	/// credential store of this one. The new credentials are saved if there's a
+	/// credential store.
+	///
+	/// # Errors
+	///
+	/// If the API rejects the credentials, [`ApiError::InvalidCredentials`],
+	/// if the request fails otherwise, or if saving the credentials fails.
+	pub async fn login(
+		&self, auth: impl Into<AuthType> + Send,
+	) -> Result<(AuthenticatedCVR, UserAuth), ApiError> {
+		let user_auth = self.authenticate(auth.into()).await?;
+		let client = self.authenticated(SavedLoginCredentials::from(&user_auth))?;
+		Ok((client, user_auth))
+	}
+
+	/// Logs in and saves the new credentials, without creating a client
+	async fn authenticate(&self, auth: AuthType) -> Result<UserAuth, ApiError> {
+		let user_auth: UserAuth = match self.query(auth).await {
+			Ok(response) => response.data,
+			Err(ApiError::Api { status, message })
+				if status.is_client_error()
+					&& status != http::StatusCode::TOO_MANY_REQUESTS =>
+			{
+				return Err(ApiError::InvalidCredentials(message));
+			}
+			Err(err) => return Err(err),
+		};
+		self.save_credentials(&SavedLoginCredentials::from(&user_auth))?;
+		Ok(user_auth)
+	}
+
+	fn authenticated(
+		&self, auth: SavedLoginCredentials,
+	) -> Result<AuthenticatedCVR, ApiError> {
+		let mut client = AuthenticatedCVR::with_rate_limiters(
+			self.config.clone(),
 			auth,
-			config: self.config,
-		})
+			self.rate_limiters.clone(),
+		)?;
+		client.backend = self.backend.clone();
+		client.cache.clone_from(&self.cache);
+		client.middleware = self.middleware.clone();
+		client.credential_store.clone_from(&self.credential_store);
+		Ok(client)
 	}
 
 	/// Creates a new CVR API client
@@ -419,10 +1262,65 @@ impl UnauthenticatedCVR {
 	/// If deserializing user agent into a header fails,
 	/// or if WS API is enabled & the connection establishment fails.
 	pub fn new(config: ApiConfiguration) -> Result<Self, ApiError> {
+		let rate_limiters = Arc::new(RateLimiters::new(&config.rate_limits));
+		Self::with_rate_limiters(config, rate_limiters)
+	}
+
+	/// Creates a new CVR API client that shares its rate limits with other
+	/// clients
+	///
+	/// # Errors
+	///
+	/// If deserializing user agent into a header fails.
+	pub fn with_rate_limiter(
+		config: ApiConfiguration, rate_limiter: &SharedRateLimiter,
+	) -> Result<Self, ApiError> {
+		let rate_limiters = rate_limiter.for_url(&config.http_url);
+		Self::with_rate_limiters(config, rate_limiters)
+	}
+
+	fn with_rate_limiters(
+		config: ApiConfiguration, rate_limiters: Arc<RateLimiters>,
+	) -> Result<Self, ApiError> {
 		Ok(Self {
-			http: Self::http_client(&config.user_agent)?,
-			http_rate_limiter: http_rate_limiter(),
+			headers: header_map("config", config.to_headers())?,
+			backend: backend::default_backend()?,
+			rate_limiters,
+			cache: None,
+			middleware: middleware::Chain::default(),
+			credential_store: None,
 			config,
 		})
 	}
+
+	/// Adds middleware to the API client, to be run after the already added
+	/// ones
+	///
+	/// The middleware is kept when the client is upgraded.
+	#[must_use]
+	pub fn with_middleware(
+		mut self, middleware: impl Middleware + 'static,
+	) -> Self {
+		self.middleware.push(Arc::new(middleware));
+		self
+	}
+
+	/// Sends the HTTP requests with the backend instead of the default one
+	///
+	/// The backend is kept when the client is upgraded or logs in.
+	#[must_use]
+	pub fn with_http_backend(mut self, backend: Arc<dyn HttpBackend>) -> Self {
+		self.backend = backend;
+		self
+	}
+
+	/// Caches the responses of read-only queries
+	///
+	/// The cache is kept when the client is upgraded, with the responses being
+	/// cached separately for each user.
+	#[must_use]
+	pub fn with_cache(mut self, cache: ResponseCache) -> Self {
+		self.cache = Some(cache);
+		self
+	}
 }
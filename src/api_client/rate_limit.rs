@@ -0,0 +1,166 @@
+use std::{
+	collections::HashMap,
+	num::NonZeroU32,
+	sync::{Arc, Mutex},
+};
+
+use governor::{
+	Quota,
+	RateLimiter,
+	clock::{Clock, DefaultClock},
+	middleware::NoOpMiddleware,
+	state::{InMemoryState, NotKeyed},
+};
+use serde::{Deserialize, Serialize};
+
+/// How many requests are allowed to be sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct RateLimitQuota {
+	/// How many requests can be sent per minute when sustained
+	pub per_minute: NonZeroU32,
+	/// How many requests can be sent at once
+	pub burst: NonZeroU32,
+}
+
+impl RateLimitQuota {
+	/// Creates a new rate limit quota
+	#[must_use]
+	pub const fn new(per_minute: NonZeroU32, burst: NonZeroU32) -> Self {
+		Self { per_minute, burst }
+	}
+
+	fn limiter(self) -> Limiter {
+		RateLimiter::direct(
+			Quota::per_minute(self.per_minute).allow_burst(self.burst),
+		)
+	}
+}
+
+impl Default for RateLimitQuota {
+	fn default() -> Self {
+		// ~5 seconds per request sustained over one minute, allowing up to a
+		// request per second in bursts.
+		Self::new(NonZeroU32::new(12).unwrap(), NonZeroU32::new(5).unwrap())
+	}
+}
+
+/// The rate limits of the API client
+///
+/// Requests that don't have a separate quota configured share the default
+/// one.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct RateLimits {
+	/// The quota of requests that don't have a separate one
+	pub default: RateLimitQuota,
+	/// A separate quota for searches
+	pub search: Option<RateLimitQuota>,
+	/// A separate quota for looking up users
+	pub user_lookup: Option<RateLimitQuota>,
+	/// A separate quota for WS connection attempts, including reconnects
+	pub ws_connect: Option<RateLimitQuota>,
+}
+
+type Limiter =
+	RateLimiter<NotKeyed, InMemoryState, DefaultClock, NoOpMiddleware>;
+
+/// Which quota a request counts against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Route {
+	Default,
+	Search,
+	UserLookup,
+	#[cfg_attr(not(feature = "ws_client"), allow(dead_code))]
+	WsConnect,
+}
+
+impl Route {
+	/// Figures out the route of a HTTP request from its URL
+	pub fn of(url: &str) -> Self {
+		let path = url.strip_prefix(crate::API_V1_HTTP_URL).unwrap_or(url);
+		if path.starts_with("/search/") {
+			Self::Search
+		} else if path
+			.strip_prefix("/users/")
+			.is_some_and(|rest| rest != "auth" && !rest.contains('/'))
+		{
+			Self::UserLookup
+		} else {
+			Self::Default
+		}
+	}
+}
+
+/// The rate limiters for every configured quota
+#[derive(Debug)]
+pub struct RateLimiters {
+	default: Limiter,
+	search: Option<Limiter>,
+	user_lookup: Option<Limiter>,
+	ws_connect: Option<Limiter>,
+}
+
+impl RateLimiters {
+	pub fn new(limits: &RateLimits) -> Self {
+		Self {
+			default: limits.default.limiter(),
+			search: limits.search.map(RateLimitQuota::limiter),
+			user_lookup: limits.user_lookup.map(RateLimitQuota::limiter),
+			ws_connect: limits.ws_connect.map(RateLimitQuota::limiter),
+		}
+	}
+
+	/// Waits until a request of the route is allowed to be sent
+	pub async fn until_ready(&self, route: Route) {
+		let limiter = match route {
+			Route::Default => None,
+			Route::Search => self.search.as_ref(),
+			Route::UserLookup => self.user_lookup.as_ref(),
+			Route::WsConnect => self.ws_connect.as_ref(),
+		};
+		let limiter = limiter.unwrap_or(&self.default);
+		// Not using `until_ready`, as its timer doesn't work in browsers
+		while let Err(not_until) = limiter.check() {
+			super::rt::sleep(not_until.wait_time_from(limiter.clock().now())).await;
+		}
+	}
+}
+
+/// Rate limits that are shared between many API clients
+///
+/// Clients that talk to the same API host count against the same quotas, so
+/// that managing multiple accounts or recreating clients doesn't exceed the
+/// limits of the API. Cloning this returns a handle to the same rate limits.
+#[derive(Debug, Clone, Default)]
+pub struct SharedRateLimiter {
+	limits: RateLimits,
+	hosts: Arc<Mutex<HashMap<String, Arc<RateLimiters>>>>,
+}
+
+impl SharedRateLimiter {
+	/// Creates new rate limits to share, with the quotas being used instead of
+	/// the ones in the configuration of the clients
+	#[must_use]
+	pub fn new(limits: RateLimits) -> Self {
+		Self { limits, hosts: Arc::default() }
+	}
+
+	/// The quotas that are enforced for each host
+	#[must_use]
+	pub const fn limits(&self) -> &RateLimits { &self.limits }
+
+	/// The rate limiters of the host of an API URL
+	pub(super) fn for_url(&self, url: &str) -> Arc<RateLimiters> {
+		let host = url.split_once("://").map_or(url, |(_, rest)| rest);
+		let host = host.split('/').next().unwrap_or(host);
+		self
+			.hosts
+			.lock()
+			.unwrap_or_else(std::sync::PoisonError::into_inner)
+			.entry(host.to_owned())
+			.or_insert_with(|| Arc::new(RateLimiters::new(&self.limits)))
+			.clone()
+	}
+}
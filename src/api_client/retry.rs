@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// How failed HTTP queries should be retried
+///
+/// Only idempotent queries (`GET` & `HEAD`) are ever retried, and only if the
+/// API is rate limiting the client or seems to be temporarily unavailable.
+/// Logging in for example is never retried.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct RetryPolicy {
+	/// How many times a query is retried at most, `0` to disable retrying
+	pub max_retries: u32,
+	/// How long to wait before the first retry
+	pub initial_delay: Duration,
+	/// The longest amount of time to wait between retries
+	pub max_delay: Duration,
+	/// How much the delay grows with each retry
+	pub multiplier: f64,
+	/// How much random variance is added to each delay, as a fraction of it
+	pub jitter: f64,
+	/// The longest `Retry-After` of a response that is still waited for,
+	/// anything longer than this is returned as an error right away
+	pub max_retry_after: Duration,
+}
+
+impl Default for RetryPolicy {
+	fn default() -> Self {
+		Self {
+			max_retries: 3,
+			initial_delay: Duration::from_millis(500),
+			max_delay: Duration::from_secs(30),
+			multiplier: 2.0,
+			jitter: 0.2,
+			max_retry_after: Duration::from_mins(1),
+		}
+	}
+}
+
+impl RetryPolicy {
+	/// A policy that never retries anything
+	#[must_use]
+	pub fn never() -> Self { Self { max_retries: 0, ..Self::default() } }
+
+	/// How long to wait before the retry number `attempt`, starting from 1
+	#[must_use]
+	pub fn delay(&self, attempt: u32) -> Duration {
+		super::backoff::exponential(
+			self.initial_delay,
+			self.max_delay,
+			self.multiplier,
+			self.jitter,
+			attempt,
+		)
+	}
+
+	/// How long to wait before the retry number `attempt`, if it should happen
+	/// at all
+	pub(super) fn next_delay(
+		&self, attempt: u32, retry_after: Option<Duration>,
+	) -> Option<Duration> {
+		if attempt > self.max_retries {
+			return None;
+		}
+		match retry_after {
+			Some(retry_after) if retry_after > self.max_retry_after => None,
+			Some(retry_after) => Some(retry_after),
+			None => Some(self.delay(attempt)),
+		}
+	}
+}
+
+/// If a response with the status code is worth retrying
+pub(super) fn is_retryable_status(status: http::StatusCode) -> bool {
+	matches!(
+		status,
+		http::StatusCode::TOO_MANY_REQUESTS
+			| http::StatusCode::INTERNAL_SERVER_ERROR
+			| http::StatusCode::BAD_GATEWAY
+			| http::StatusCode::SERVICE_UNAVAILABLE
+			| http::StatusCode::GATEWAY_TIMEOUT
+	)
+}
+
+/// Parses the `Retry-After` header, which is either in seconds or a HTTP date
+pub(super) fn retry_after(headers: &http::HeaderMap) -> Option<Duration> {
+	let value = headers.get(http::header::RETRY_AFTER)?.to_str().ok()?.trim();
+	if let Ok(seconds) = value.parse::<u64>() {
+		return Some(Duration::from_secs(seconds));
+	}
+
+	let date = time::OffsetDateTime::parse(
+		value,
+		&time::format_description::well_known::Rfc2822,
+	)
+	.ok()?;
+	let now = time::OffsetDateTime::UNIX_EPOCH
+		+ super::rt::SystemTime::now()
+			.duration_since(super::rt::UNIX_EPOCH)
+			.unwrap_or_default();
+	// A date in the past means that it's fine to retry right away
+	Some((date - now).try_into().unwrap_or_default())
+}
@@ -0,0 +1,74 @@
+//! The parts of an async runtime that the API clients need, which are provided
+//! by tokio natively and by the browser on `wasm32`
+
+#[cfg(feature = "ws_client")]
+use std::future::Future;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+pub use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+#[cfg(target_arch = "wasm32")]
+pub use web_time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Waits for the duration
+pub async fn sleep(duration: Duration) {
+	#[cfg(not(target_arch = "wasm32"))]
+	tokio::time::sleep(duration).await;
+	// The timers are JS objects, but there's only a single thread anyway
+	#[cfg(target_arch = "wasm32")]
+	send_wrapper::SendWrapper::new(gloo_timers::future::sleep(duration)).await;
+}
+
+/// Runs the future, unless it takes longer than the duration
+#[cfg(feature = "ws_client")]
+pub async fn timeout<T>(
+	duration: Duration, future: impl Future<Output = T>,
+) -> Option<T> {
+	tokio::select! {
+		output = future => Some(output),
+		() = sleep(duration) => None,
+	}
+}
+
+/// Runs the future in the background
+#[cfg(all(feature = "ws_client", not(target_arch = "wasm32")))]
+pub fn spawn(future: impl Future<Output = ()> + Send + 'static) -> JoinHandle {
+	JoinHandle(tokio::spawn(future))
+}
+
+/// Runs the future in the background
+#[cfg(all(feature = "ws_client", target_arch = "wasm32"))]
+pub fn spawn(future: impl Future<Output = ()> + 'static) -> JoinHandle {
+	let aborted = std::sync::Arc::new(tokio::sync::Notify::new());
+	let abort = aborted.clone();
+	wasm_bindgen_futures::spawn_local(async move {
+		tokio::select! {
+			() = future => {}
+			() = abort.notified() => {}
+		}
+	});
+	JoinHandle(aborted)
+}
+
+/// A handle to a future that was spawned in the background, which keeps
+/// running even if this is dropped
+#[cfg(all(feature = "ws_client", not(target_arch = "wasm32")))]
+#[derive(Debug)]
+pub struct JoinHandle(tokio::task::JoinHandle<()>);
+
+/// A handle to a future that was spawned in the background, which keeps
+/// running even if this is dropped
+#[cfg(all(feature = "ws_client", target_arch = "wasm32"))]
+#[derive(Debug)]
+pub struct JoinHandle(std::sync::Arc<tokio::sync::Notify>);
+
+#[cfg(feature = "ws_client")]
+impl JoinHandle {
+	/// Stops the future the next time that it yields
+	pub fn abort(&self) {
+		#[cfg(not(target_arch = "wasm32"))]
+		self.0.abort();
+		#[cfg(target_arch = "wasm32")]
+		self.0.notify_one();
+	}
+}
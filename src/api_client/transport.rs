@@ -0,0 +1,419 @@
+use async_trait::async_trait;
+#[cfg(not(target_arch = "wasm32"))]
+use ezsockets::client::ClientCloseMode;
+use tokio::sync::mpsc::{
+	UnboundedReceiver,
+	UnboundedSender,
+	unbounded_channel,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::{sync::oneshot, task::JoinHandle};
+
+use super::ApiError;
+
+/// A WS frame that carries data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WsFrame {
+	/// A text frame
+	Text(String),
+	/// A binary frame
+	Binary(Vec<u8>),
+}
+
+impl WsFrame {
+	/// The data of the frame
+	#[must_use]
+	pub fn as_bytes(&self) -> &[u8] {
+		match self {
+			Self::Text(text) => text.as_bytes(),
+			Self::Binary(bytes) => bytes,
+		}
+	}
+}
+
+/// A way of opening WS connections
+///
+/// The API client handles reconnecting, keepalives and everything else on top
+/// of the connections, so a transport only needs to move frames around.
+/// [`EzsocketsTransport`] is used by default, or `WebSocketTransport` in
+/// browsers, with [`MemoryTransport`] being useful for testing without any
+/// network.
+#[async_trait]
+pub trait WsTransport: Send + Sync {
+	/// Opens a new connection, with the headers added to the handshake
+	///
+	/// # Errors
+	///
+	/// If the connection can't be opened.
+	async fn connect(
+		&self, url: &str, headers: &http::HeaderMap,
+	) -> Result<Box<dyn WsConnection>, ApiError>;
+}
+
+/// An open WS connection of a [`WsTransport`]
+#[async_trait]
+pub trait WsConnection: Send {
+	/// Sends a frame
+	///
+	/// # Errors
+	///
+	/// If the connection has dropped.
+	async fn send(&mut self, frame: WsFrame) -> Result<(), ApiError>;
+
+	/// Waits for the next frame, `None` if the connection was closed
+	///
+	/// This has to be cancellation safe, as it's raced against sending frames.
+	async fn receive(&mut self) -> Option<Result<WsFrame, ApiError>>;
+
+	/// Closes the connection
+	async fn close(&mut self);
+}
+
+/// The default transport, using [`ezsockets`]
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EzsocketsTransport;
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl WsTransport for EzsocketsTransport {
+	async fn connect(
+		&self, url: &str, headers: &http::HeaderMap,
+	) -> Result<Box<dyn WsConnection>, ApiError> {
+		use serde::ser::Error;
+
+		let ws_url = url::Url::parse(url).map_err(|e| {
+			serde_json::Error::custom(
+				"Couldn't parse configured WS URL: ".to_string() + &e.to_string(),
+			)
+		})?;
+		let mut ws_config =
+			ezsockets::ClientConfig::new(ws_url).max_initial_connect_attempts(1);
+		for (header_name, header_value) in headers {
+			// TODO: Remove as_* once ezsockets updates the http to >1
+			ws_config =
+				ws_config.header(header_name.as_str(), header_value.as_bytes());
+		}
+
+		let (connected_sender, connected_receiver) = oneshot::channel();
+		let (received_sender, received) = unbounded_channel();
+		let client_ext =
+			InternalClientExt { received_sender, connected: Some(connected_sender) };
+		let (internal, future) =
+			ezsockets::connect(|_client| client_ext, ws_config).await;
+		let closed = tokio::spawn(future);
+
+		match connected_receiver.await {
+			Ok(Ok(())) => Ok(Box::new(EzsocketsConnection {
+				internal,
+				received,
+				closed: Some(closed),
+			})),
+			Ok(Err(err)) => Err(err),
+			// The client stopped without reporting back, so it must've errored
+			Err(_) => Err(match closed.await {
+				Ok(Err(err)) => ApiError::WebSocket(err),
+				Ok(Ok(())) => ApiError::WebSocket("WS connection closed".into()),
+				Err(err) => ApiError::WebSocket(Box::new(err)),
+			}),
+		}
+	}
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+struct EzsocketsConnection {
+	internal: ezsockets::Client<InternalClientExt>,
+	received: UnboundedReceiver<WsFrame>,
+	/// `None` once the client has stopped
+	closed: Option<JoinHandle<Result<(), ezsockets::Error>>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl WsConnection for EzsocketsConnection {
+	async fn send(&mut self, frame: WsFrame) -> Result<(), ApiError> {
+		let sent = match frame {
+			WsFrame::Text(text) => self.internal.text(text),
+			WsFrame::Binary(bytes) => self.internal.binary(bytes),
+		};
+		sent.map(drop).map_err(|e| ApiError::WebSocket(Box::new(e)))
+	}
+
+	async fn receive(&mut self) -> Option<Result<WsFrame, ApiError>> {
+		if let Some(frame) = self.received.recv().await {
+			return Some(Ok(frame));
+		}
+		// The client has stopped, as it dropped the sender
+		let result = self.closed.as_mut()?.await;
+		self.closed = None;
+		match result {
+			Ok(Ok(())) => None,
+			Ok(Err(err)) => Some(Err(ApiError::WebSocket(err))),
+			Err(err) => Some(Err(ApiError::WebSocket(Box::new(err)))),
+		}
+	}
+
+	async fn close(&mut self) { self.internal.close(None).ok(); }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for EzsocketsConnection {
+	fn drop(&mut self) {
+		if let Some(closed) = &self.closed {
+			closed.abort();
+		}
+	}
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+struct InternalClientExt {
+	received_sender: UnboundedSender<WsFrame>,
+	connected: Option<oneshot::Sender<Result<(), ApiError>>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl ezsockets::ClientExt for InternalClientExt {
+	type Call = ();
+
+	async fn on_text(&mut self, text: String) -> Result<(), ezsockets::Error> {
+		// The connection being dropped is fine, the frame is just dropped too
+		self.received_sender.send(WsFrame::Text(text)).ok();
+		Ok(())
+	}
+
+	async fn on_binary(
+		&mut self, bytes: Vec<u8>,
+	) -> Result<(), ezsockets::Error> {
+		self.received_sender.send(WsFrame::Binary(bytes)).ok();
+		Ok(())
+	}
+
+	async fn on_call(
+		&mut self, _params: Self::Call,
+	) -> Result<(), ezsockets::Error> {
+		Ok(())
+	}
+
+	async fn on_connect(&mut self) -> Result<(), ezsockets::Error> {
+		if let Some(connected) = self.connected.take() {
+			connected.send(Ok(())).ok();
+		}
+		Ok(())
+	}
+
+	// Reconnecting is handled by the supervisor instead of ezsockets, as it
+	// only supports fixed reconnection intervals.
+
+	async fn on_connect_fail(
+		&mut self, error: ezsockets::WSError,
+	) -> Result<ClientCloseMode, ezsockets::Error> {
+		if let Some(connected) = self.connected.take() {
+			connected.send(Err(ApiError::WebSocket(Box::new(error)))).ok();
+		}
+		Ok(ClientCloseMode::Close)
+	}
+
+	async fn on_close(
+		&mut self, _frame: Option<ezsockets::CloseFrame>,
+	) -> Result<ClientCloseMode, ezsockets::Error> {
+		Ok(ClientCloseMode::Close)
+	}
+
+	async fn on_disconnect(
+		&mut self,
+	) -> Result<ClientCloseMode, ezsockets::Error> {
+		Ok(ClientCloseMode::Close)
+	}
+}
+
+/// The transport that the API clients use unless they're given one
+#[cfg(not(target_arch = "wasm32"))]
+pub(super) fn default_transport() -> std::sync::Arc<dyn WsTransport> {
+	std::sync::Arc::new(EzsocketsTransport)
+}
+
+/// The transport that the API clients use unless they're given one
+#[cfg(all(target_arch = "wasm32", feature = "web"))]
+pub(super) fn default_transport() -> std::sync::Arc<dyn WsTransport> {
+	std::sync::Arc::new(super::web::WebSocketTransport)
+}
+
+/// The transport that the API clients use unless they're given one
+#[cfg(all(target_arch = "wasm32", not(feature = "web")))]
+pub(super) fn default_transport() -> std::sync::Arc<dyn WsTransport> {
+	std::sync::Arc::new(Unconfigured)
+}
+
+/// Fails every connection, for when there's no transport to use by default
+#[cfg(all(target_arch = "wasm32", not(feature = "web")))]
+struct Unconfigured;
+
+#[cfg(all(target_arch = "wasm32", not(feature = "web")))]
+#[async_trait]
+impl WsTransport for Unconfigured {
+	async fn connect(
+		&self, _url: &str, _headers: &http::HeaderMap,
+	) -> Result<Box<dyn WsConnection>, ApiError> {
+		Err(ApiError::WebSocket(
+			"No WS transport was configured, enable the `web` feature or add one \
+			 with `with_ws_transport`"
+				.into(),
+		))
+	}
+}
+
+/// A transport that connects to a [`MemoryServer`] over in-memory channels
+///
+/// ```
+/// # async fn example() -> Result<(), chilloutvr::api_client::ApiError> {
+/// use std::sync::Arc;
+///
+/// use chilloutvr::{
+/// 	api_client::{
+/// 		ApiConfiguration,
+/// 		AuthenticatedCVR,
+/// 		MemoryTransport,
+/// 		WsFrame,
+/// 	},
+/// 	query::{Online, SavedLoginCredentials},
+/// };
+///
+/// let (transport, server) = MemoryTransport::new();
+/// let api_client = AuthenticatedCVR::new(
+/// 	ApiConfiguration::new("example".to_owned()),
+/// 	SavedLoginCredentials {
+/// 		username: "username".to_owned(),
+/// 		access_key: "access-key".to_owned(),
+/// 	},
+/// )?
+/// .with_ws_transport(Arc::new(transport));
+///
+/// api_client.ws_connect().await?;
+/// let mut peer = server.accept().await.unwrap();
+/// api_client.send(Online).await?;
+/// assert!(matches!(peer.receive().await, Some(WsFrame::Binary(_))));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MemoryTransport {
+	connections: UnboundedSender<MemoryPeer>,
+}
+
+impl MemoryTransport {
+	/// Creates a transport along with the server that it connects to
+	#[must_use]
+	pub fn new() -> (Self, MemoryServer) {
+		let (connections, accepted) = unbounded_channel();
+		(
+			Self { connections },
+			MemoryServer { accepted: tokio::sync::Mutex::new(accepted) },
+		)
+	}
+}
+
+#[async_trait]
+impl WsTransport for MemoryTransport {
+	async fn connect(
+		&self, url: &str, headers: &http::HeaderMap,
+	) -> Result<Box<dyn WsConnection>, ApiError> {
+		let (client_sender, server_receiver) = unbounded_channel();
+		let (server_sender, client_receiver) = unbounded_channel();
+		self
+			.connections
+			.send(MemoryPeer {
+				url: url.to_owned(),
+				headers: headers.clone(),
+				sender: Some(server_sender),
+				receiver: server_receiver,
+			})
+			.map_err(|_| {
+				ApiError::WebSocket("The memory server was dropped".into())
+			})?;
+		Ok(Box::new(MemoryConnection {
+			sender: Some(client_sender),
+			receiver: client_receiver,
+		}))
+	}
+}
+
+/// The server that [`MemoryTransport`]s connect to
+#[derive(Debug)]
+pub struct MemoryServer {
+	accepted: tokio::sync::Mutex<UnboundedReceiver<MemoryPeer>>,
+}
+
+impl MemoryServer {
+	/// Waits for the next connection, `None` if every transport was dropped
+	pub async fn accept(&self) -> Option<MemoryPeer> {
+		self.accepted.lock().await.recv().await
+	}
+}
+
+/// The server's end of a [`MemoryTransport`] connection
+///
+/// Dropping this drops the connection.
+#[derive(Debug)]
+pub struct MemoryPeer {
+	url: String,
+	headers: http::HeaderMap,
+	/// `None` once closed
+	sender: Option<UnboundedSender<WsFrame>>,
+	receiver: UnboundedReceiver<WsFrame>,
+}
+
+impl MemoryPeer {
+	/// The URL that the client connected to
+	#[must_use]
+	pub fn url(&self) -> &str { &self.url }
+
+	/// The headers of the client's handshake
+	#[must_use]
+	pub const fn headers(&self) -> &http::HeaderMap { &self.headers }
+
+	/// Sends a frame to the client
+	///
+	/// # Errors
+	///
+	/// If the connection was closed, returning the frame back.
+	pub fn send(&self, frame: WsFrame) -> Result<(), WsFrame> {
+		match &self.sender {
+			Some(sender) => sender.send(frame).map_err(|err| err.0),
+			None => Err(frame),
+		}
+	}
+
+	/// Waits for the next frame from the client, `None` if the connection was
+	/// closed
+	pub async fn receive(&mut self) -> Option<WsFrame> {
+		self.receiver.recv().await
+	}
+
+	/// Closes the connection, while still allowing receiving the frames that
+	/// the client sent before
+	pub fn close(&mut self) { self.sender = None; }
+}
+
+struct MemoryConnection {
+	/// `None` once closed
+	sender: Option<UnboundedSender<WsFrame>>,
+	receiver: UnboundedReceiver<WsFrame>,
+}
+
+#[async_trait]
+impl WsConnection for MemoryConnection {
+	async fn send(&mut self, frame: WsFrame) -> Result<(), ApiError> {
+		self
+			.sender
+			.as_ref()
+			.and_then(|sender| sender.send(frame).ok())
+			.ok_or_else(|| ApiError::WebSocket("WS connection closed".into()))
+	}
+
+	async fn receive(&mut self) -> Option<Result<WsFrame, ApiError>> {
+		self.receiver.recv().await.map(Ok)
+	}
+
+	async fn close(&mut self) { self.sender = None; }
+}
@@ -0,0 +1,245 @@
+//! Browser versions of the HTTP backend & WS transport
+//!
+//! Browsers don't allow setting headers on WS handshakes, so the connections of
+//! [`WebSocketTransport`] lack the authentication headers of the CVR API, and
+//! need to go through a proxy that adds them instead. The HTTP requests of
+//! [`FetchBackend`] are subject to the CORS rules of the browser as usual.
+//!
+//! Everything here is single threaded like the browsers are, with the JS
+//! objects being wrapped to satisfy the `Send` bounds of the API clients.
+
+use send_wrapper::SendWrapper;
+use wasm_bindgen::{JsCast, JsValue, prelude::*};
+use wasm_bindgen_futures::JsFuture;
+
+#[wasm_bindgen]
+extern "C" {
+	/// The `fetch` of both windows & workers
+	#[wasm_bindgen(js_name = fetch)]
+	fn fetch_with_request(request: &web_sys::Request) -> js_sys::Promise;
+}
+
+/// The message of a JS error
+fn js_error(value: &JsValue) -> String {
+	value.dyn_ref::<js_sys::Error>().map_or_else(
+		|| value.as_string().unwrap_or_else(|| format!("{value:?}")),
+		|err| err.message().into(),
+	)
+}
+
+/// A backend using the `fetch` API of browsers
+#[cfg(feature = "http_client_core")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FetchBackend;
+
+#[cfg(feature = "http_client_core")]
+#[async_trait::async_trait]
+impl super::HttpBackend for FetchBackend {
+	async fn execute(
+		&self, request: super::HttpRequest,
+	) -> Result<super::HttpResponse, super::HttpError> {
+		SendWrapper::new(fetch(request)).await
+	}
+}
+
+// Not `Send`, which is why it's wrapped
+#[cfg(feature = "http_client_core")]
+#[allow(clippy::future_not_send)]
+async fn fetch(
+	request: super::HttpRequest,
+) -> Result<super::HttpResponse, super::HttpError> {
+	use super::HttpError;
+
+	let http_error = |err: JsValue| HttpError::new(js_error(&err));
+
+	let (parts, body) = request.into_parts();
+	let headers = web_sys::Headers::new().map_err(http_error)?;
+	for (name, value) in &parts.headers {
+		let value = value.to_str().map_err(HttpError::new)?;
+		headers.append(name.as_str(), value).map_err(http_error)?;
+	}
+	let init = web_sys::RequestInit::new();
+	init.set_method(parts.method.as_str());
+	init.set_headers(&headers);
+	if !body.is_empty() {
+		init.set_body(&js_sys::Uint8Array::from(body.as_slice()));
+	}
+	let request =
+		web_sys::Request::new_with_str_and_init(&parts.uri.to_string(), &init)
+			.map_err(http_error)?;
+
+	// Fetch only fails if the request couldn't be sent at all
+	let response: web_sys::Response =
+		JsFuture::from(fetch_with_request(&request))
+			.await
+			.map_err(|err| HttpError::transient(js_error(&err)))?
+			.unchecked_into();
+	let mut builder = http::Response::builder().status(response.status());
+	if let Some(entries) =
+		js_sys::try_iter(&response.headers()).map_err(http_error)?
+	{
+		for entry in entries {
+			let entry = js_sys::Array::from(&entry.map_err(http_error)?);
+			if let (Some(name), Some(value)) =
+				(entry.get(0).as_string(), entry.get(1).as_string())
+			{
+				builder = builder.header(name, value);
+			}
+		}
+	}
+	let body = JsFuture::from(response.array_buffer().map_err(http_error)?)
+		.await
+		.map_err(http_error)?;
+	builder.body(js_sys::Uint8Array::new(&body).to_vec()).map_err(HttpError::new)
+}
+
+/// A transport using the `WebSocket` API of browsers
+///
+/// The headers of the handshake are left out, as browsers don't allow setting
+/// them.
+#[cfg(feature = "ws_client")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WebSocketTransport;
+
+#[cfg(feature = "ws_client")]
+#[async_trait::async_trait]
+impl super::WsTransport for WebSocketTransport {
+	async fn connect(
+		&self, url: &str, _headers: &http::HeaderMap,
+	) -> Result<Box<dyn super::WsConnection>, super::ApiError> {
+		SendWrapper::new(WebSocketConnection::open(url)).await
+	}
+}
+
+/// Sends the events of a WS connection onwards
+#[cfg(feature = "ws_client")]
+type Handler = Closure<dyn FnMut(JsValue)>;
+
+/// What happened with a WS connection
+#[cfg(feature = "ws_client")]
+enum Event {
+	Open,
+	Frame(super::WsFrame),
+	Error,
+	Close(String),
+}
+
+#[cfg(feature = "ws_client")]
+struct WebSocketConnection {
+	socket: SendWrapper<web_sys::WebSocket>,
+	/// Kept alive for as long as the socket might call them
+	_handlers: SendWrapper<[Handler; 4]>,
+	events: tokio::sync::mpsc::UnboundedReceiver<Event>,
+}
+
+#[cfg(feature = "ws_client")]
+impl WebSocketConnection {
+	// Not `Send`, which is why it's wrapped
+	#[allow(clippy::future_not_send)]
+	async fn open(
+		url: &str,
+	) -> Result<Box<dyn super::WsConnection>, super::ApiError> {
+		use super::ApiError;
+
+		let socket = web_sys::WebSocket::new(url)
+			.map_err(|err| ApiError::WebSocket(js_error(&err).into()))?;
+		socket.set_binary_type(web_sys::BinaryType::Arraybuffer);
+
+		let (sender, events) = tokio::sync::mpsc::unbounded_channel();
+		let handler = |event: fn(JsValue) -> Event| {
+			let sender = sender.clone();
+			Handler::new(move |value| {
+				// The connection being dropped is fine, the event is just dropped too
+				sender.send(event(value)).ok();
+			})
+		};
+		let handlers = [
+			handler(|_| Event::Open),
+			handler(frame),
+			handler(|_| Event::Error),
+			handler(|event| {
+				let event = event.unchecked_into::<web_sys::CloseEvent>();
+				Event::Close(format!("{} {}", event.code(), event.reason()))
+			}),
+		];
+		socket.set_onopen(Some(handlers[0].as_ref().unchecked_ref()));
+		socket.set_onmessage(Some(handlers[1].as_ref().unchecked_ref()));
+		socket.set_onerror(Some(handlers[2].as_ref().unchecked_ref()));
+		socket.set_onclose(Some(handlers[3].as_ref().unchecked_ref()));
+
+		let mut connection = Self {
+			socket: SendWrapper::new(socket),
+			_handlers: SendWrapper::new(handlers),
+			events,
+		};
+		loop {
+			match connection.events.recv().await {
+				Some(Event::Open) => return Ok(Box::new(connection)),
+				Some(Event::Close(reason)) => {
+					return Err(ApiError::WebSocket(
+						format!("WS connection closed: {reason}").into(),
+					));
+				}
+				// Errors are followed by the connection closing
+				Some(Event::Frame(_) | Event::Error) => {}
+				None => {
+					return Err(ApiError::WebSocket("WS connection closed".into()));
+				}
+			}
+		}
+	}
+}
+
+/// The frame of a message event
+#[cfg(feature = "ws_client")]
+fn frame(event: JsValue) -> Event {
+	let data = event.unchecked_into::<web_sys::MessageEvent>().data();
+	Event::Frame(data.as_string().map_or_else(
+		|| super::WsFrame::Binary(js_sys::Uint8Array::new(&data).to_vec()),
+		super::WsFrame::Text,
+	))
+}
+
+#[cfg(feature = "ws_client")]
+#[async_trait::async_trait]
+impl super::WsConnection for WebSocketConnection {
+	async fn send(
+		&mut self, frame: super::WsFrame,
+	) -> Result<(), super::ApiError> {
+		match frame {
+			super::WsFrame::Text(text) => self.socket.send_with_str(&text),
+			super::WsFrame::Binary(bytes) => self.socket.send_with_u8_array(&bytes),
+		}
+		.map_err(|err| super::ApiError::WebSocket(js_error(&err).into()))
+	}
+
+	async fn receive(
+		&mut self,
+	) -> Option<Result<super::WsFrame, super::ApiError>> {
+		loop {
+			match self.events.recv().await? {
+				Event::Frame(frame) => return Some(Ok(frame)),
+				Event::Error => {
+					return Some(Err(super::ApiError::WebSocket(
+						"WS connection errored".into(),
+					)));
+				}
+				Event::Close(_) => return None,
+				Event::Open => {}
+			}
+		}
+	}
+
+	async fn close(&mut self) { self.socket.close().ok(); }
+}
+
+#[cfg(feature = "ws_client")]
+impl Drop for WebSocketConnection {
+	fn drop(&mut self) {
+		self.socket.set_onopen(None);
+		self.socket.set_onmessage(None);
+		self.socket.set_onerror(None);
+		self.socket.set_onclose(None);
+		self.socket.close().ok();
+	}
+}
@@ -1,126 +1,670 @@
-use async_trait::async_trait;
+use std::{
+	collections::VecDeque,
+	future::Future,
+	pin::Pin,
+	sync::Arc,
+	task::{Context, Poll},
+	time::Duration,
+};
+
 use serde::Serialize;
-use tokio::{sync::mpsc::UnboundedSender, task::JoinHandle};
-use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio::sync::{
+	broadcast,
+	mpsc::{UnboundedReceiver, UnboundedSender},
+	watch,
+};
+use tokio_stream::{
+	Stream,
+	wrappers::{BroadcastStream, WatchStream, errors::BroadcastStreamRecvError},
+};
 
-use super::{ApiConfiguration, ApiError};
-use crate::{model::WsResponse, query::SavedLoginCredentials};
+use super::{
+	ApiConfiguration,
+	ApiError,
+	middleware::Chain,
+	rate_limit::{RateLimiters, Route},
+	rt::{self, Instant, JoinHandle},
+	transport::{WsConnection, WsFrame, WsTransport},
+};
+use crate::{
+	model::WsResponse,
+	query::{Confirmable, Online, Requestable, SavedLoginCredentials},
+};
 
-type WsListenItem = Result<WsResponse, ApiError>;
-pub type ReceiverContainer =
-	std::sync::Arc<tokio::sync::Mutex<UnboundedReceiverStream<WsListenItem>>>;
+/// A received WS frame, or the reason for the connection being given up on
+type ReceivedFrame = Result<Arc<[u8]>, Arc<str>>;
 
-pub struct Client {
-	receive: ReceiverContainer,
-	handle: JoinHandle<()>,
-	internal: ezsockets::Client<InternalClientExt>,
+/// The credentials to connect with, which may change between reconnects
+type Credentials = Arc<std::sync::RwLock<SavedLoginCredentials>>;
+
+/// What the supervisor of the connection is asked to do
+enum Outgoing {
+	/// Send a frame
+	Frame(Vec<u8>),
+	/// Reconnect right away, to use new credentials
+	Reconnect,
+}
+
+/// Why [`Connection::run`] stopped
+enum Stopped {
+	/// The connection was closed on purpose
+	Closed,
+	/// A reconnection was requested
+	Reconnect,
+	/// The connection dropped
+	Dropped(ApiError),
+}
+
+/// What became of a WS request that was sent and waited for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WsOutcome {
+	/// The API responded with the update that confirms the request
+	Confirmed(WsResponse),
+	/// The request was sent and wasn't rejected in time, but the API doesn't
+	/// respond to it with anything that would confirm it
+	Unconfirmed,
 }
 
-struct InternalClientExt {
-	received_sender: UnboundedSender<WsListenItem>,
+/// A stream of the messages received from the WS connection
+///
+/// Every listener gets its own copy of each message. The messages are buffered
+/// up to the configured capacity, after which a listener that falls behind
+/// skips the oldest ones and gets an [`ApiError::Lagged`] instead.
+#[derive(Debug)]
+pub struct WsListener {
+	inner: BroadcastStream<ReceivedFrame>,
 }
 
-impl InternalClientExt {
-	/// Turns a WS receiving channel to an async streams
-	fn send_ws_msg(&self, bytes: &[u8]) {
-		let res: WsListenItem =
-			serde_json::from_slice::<WsResponse>(bytes).map_err(ApiError::from);
-		self.received_sender.send(res).ok();
+impl WsListener {
+	/// Waits for the update that confirms the request, or for a popup that
+	/// rejects it
+	///
+	/// Messages that fail to deserialize or are skipped over are ignored, as
+	/// they might be unrelated to the request, as are popups that aren't about
+	/// it.
+	pub(super) async fn confirmation(
+		&mut self, request: &(impl Confirmable + Sync),
+	) -> Result<WsResponse, ApiError> {
+		use tokio_stream::StreamExt;
+
+		while let Some(response) = self.next().await {
+			let response = match response {
+				Ok(response) => response,
+				Err(ApiError::Serde(_) | ApiError::Lagged(_)) => continue,
+				Err(err) => return Err(err),
+			};
+			if request.is_confirmed_by(&response.data) {
+				return Ok(response);
+			}
+			if request.is_rejected_by(&response.data) {
+				return Err(ApiError::Rejected(Box::new(response)));
+			}
+		}
+		Err(ApiError::WebSocket("WS connection closed".into()))
 	}
 }
-#[async_trait]
-impl ezsockets::ClientExt for InternalClientExt {
-	type Call = ();
 
-	async fn on_text(&mut self, text: String) -> Result<(), ezsockets::Error> {
-		self.send_ws_msg(text.as_bytes());
-		Ok(())
+impl Stream for WsListener {
+	type Item = Result<WsResponse, ApiError>;
+
+	fn poll_next(
+		mut self: Pin<&mut Self>, cx: &mut Context<'_>,
+	) -> Poll<Option<Self::Item>> {
+		Pin::new(&mut self.inner).poll_next(cx).map(|item| {
+			item.map(|frame| match frame {
+				Ok(Ok(bytes)) => {
+					serde_json::from_slice::<WsResponse>(&bytes).map_err(ApiError::from)
+				}
+				Ok(Err(reason)) => Err(ApiError::WebSocket(reason.as_ref().into())),
+				Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+					Err(ApiError::Lagged(skipped))
+				}
+			})
+		})
 	}
+}
 
-	async fn on_binary(
-		&mut self, bytes: Vec<u8>,
-	) -> Result<(), ezsockets::Error> {
-		self.send_ws_msg(&bytes);
-		Ok(())
+/// The state of the WS connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConnectionState {
+	/// The connection is being opened, either for the first time or to use new
+	/// credentials
+	Connecting,
+	/// The connection is open
+	Connected,
+	/// The connection dropped or couldn't be opened
+	Disconnected {
+		/// Why the connection was lost
+		reason: String,
+	},
+	/// The connection is being re-established after it was lost
+	Reconnecting {
+		/// The number of the reconnection attempt, starting from 1
+		attempt: u32,
+	},
+	/// The connection was closed on purpose, or was never opened
+	Closed,
+}
+
+/// The state of the WS connection, which outlives the connections themselves
+#[derive(Debug, Clone)]
+pub struct Status {
+	state: Arc<watch::Sender<ConnectionState>>,
+	last_error: Arc<std::sync::Mutex<Option<String>>>,
+}
+
+impl Default for Status {
+	fn default() -> Self {
+		Self {
+			state: Arc::new(watch::Sender::new(ConnectionState::Closed)),
+			last_error: Arc::default(),
+		}
 	}
+}
 
-	async fn on_call(
-		&mut self, _params: Self::Call,
-	) -> Result<(), ezsockets::Error> {
-		Ok(())
+impl Status {
+	pub fn get(&self) -> ConnectionState { self.state.borrow().clone() }
+
+	/// A stream of the current state, followed by every change to it
+	pub fn changes(&self) -> WatchStream<ConnectionState> {
+		WatchStream::new(self.state.subscribe())
+	}
+
+	pub fn last_error(&self) -> Option<String> {
+		self
+			.last_error
+			.lock()
+			.unwrap_or_else(std::sync::PoisonError::into_inner)
+			.clone()
+	}
+
+	pub fn set(&self, state: ConnectionState) {
+		if let ConnectionState::Disconnected { reason } = &state {
+			*self
+				.last_error
+				.lock()
+				.unwrap_or_else(std::sync::PoisonError::into_inner) = Some(reason.clone());
+		}
+		self.state.send_replace(state);
+	}
+
+	fn disconnected(&self, reason: &ApiError) {
+		self.set(ConnectionState::Disconnected { reason: reason.to_string() });
 	}
 }
 
-impl Client {
-	pub async fn new(
-		config: &ApiConfiguration, auth: &SavedLoginCredentials,
+/// How the WebSocket connection should be re-established after it drops
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[non_exhaustive]
+pub struct ReconnectPolicy {
+	/// If the connection should be re-established at all
+	pub enabled: bool,
+	/// How long to wait before the first reconnection attempt
+	pub initial_delay: Duration,
+	/// The upper limit of the wait between reconnection attempts
+	pub max_delay: Duration,
+	/// What the wait is multiplied by after each failed attempt
+	pub multiplier: f64,
+	/// How much random extra wait to add, as a fraction of the wait
+	pub jitter: f64,
+	/// How many failed attempts in a row to allow before giving up, `None` for
+	/// trying forever
+	pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+	fn default() -> Self {
+		Self {
+			enabled: true,
+			initial_delay: Duration::from_secs(1),
+			max_delay: Duration::from_mins(1),
+			multiplier: 2.0,
+			jitter: 0.2,
+			max_attempts: None,
+		}
+	}
+}
+
+impl ReconnectPolicy {
+	/// How long to wait before the reconnection attempt number `attempt`,
+	/// starting from 1
+	#[must_use]
+	pub fn delay(&self, attempt: u32) -> Duration {
+		super::backoff::exponential(
+			self.initial_delay,
+			self.max_delay,
+			self.multiplier,
+			self.jitter,
+			attempt,
+		)
+	}
+
+	const fn should_retry(&self, attempt: u32) -> bool {
+		if !self.enabled {
+			return false;
+		}
+		match self.max_attempts {
+			Some(max_attempts) => attempt <= max_attempts,
+			None => true,
+		}
+	}
+}
+
+/// How WS messages are queued while the connection is being re-established
+///
+/// Queued messages are sent in order once the connection is back. Messages
+/// are only queued while reconnecting, so ones sent after the client has given
+/// up on reconnecting open a new connection instead.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct WsQueuePolicy {
+	/// How many messages to queue at most, `0` to drop every message that is
+	/// sent while disconnected
+	pub capacity: usize,
+	/// How long a message can be queued for before it's dropped, `None` for
+	/// keeping them until they're sent
+	pub max_age: Option<Duration>,
+	/// Which message is dropped when the queue is full
+	pub overflow: WsQueueOverflow,
+}
+
+impl Default for WsQueuePolicy {
+	fn default() -> Self {
+		Self {
+			capacity: 64,
+			max_age: Some(Duration::from_mins(1)),
+			overflow: WsQueueOverflow::DropOldest,
+		}
+	}
+}
+
+/// Which message is dropped when the WS message queue is full
+#[derive(
+	Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize,
+)]
+#[non_exhaustive]
+pub enum WsQueueOverflow {
+	/// Drop the message that has been queued for the longest
+	DropOldest,
+	/// Drop the message that is being queued
+	DropNewest,
+}
+
+/// The messages that are waiting for the connection to be re-established
+struct Queue {
+	policy: WsQueuePolicy,
+	frames: VecDeque<(Instant, Vec<u8>)>,
+}
+
+impl Queue {
+	const fn new(policy: WsQueuePolicy) -> Self {
+		Self { policy, frames: VecDeque::new() }
+	}
+
+	fn push(&mut self, frame: Vec<u8>) {
+		self.expire();
+		if self.policy.capacity == 0 {
+			return;
+		}
+		if self.frames.len() >= self.policy.capacity {
+			match self.policy.overflow {
+				WsQueueOverflow::DropOldest => {
+					self.frames.pop_front();
+				}
+				WsQueueOverflow::DropNewest => return,
+			}
+		}
+		self.frames.push_back((Instant::now(), frame));
+	}
+
+	/// Puts a message that failed to be sent back to the front of the queue
+	fn push_front(&mut self, frame: Vec<u8>) {
+		if self.policy.capacity > 0 {
+			self.frames.push_front((Instant::now(), frame));
+			self.frames.truncate(self.policy.capacity);
+		}
+	}
+
+	fn pop(&mut self) -> Option<Vec<u8>> {
+		self.expire();
+		self.frames.pop_front().map(|(_, frame)| frame)
+	}
+
+	fn expire(&mut self) {
+		let Some(max_age) = self.policy.max_age else {
+			return;
+		};
+		while self
+			.frames
+			.front()
+			.is_some_and(|(queued, _)| queued.elapsed() > max_age)
+		{
+			self.frames.pop_front();
+		}
+	}
+}
+
+pub struct Client {
+	received: broadcast::Sender<ReceivedFrame>,
+	/// Subscribed before connecting, so that no messages are missed by it
+	first_listener: std::sync::Mutex<Option<broadcast::Receiver<ReceivedFrame>>>,
+	outgoing: UnboundedSender<Outgoing>,
+	handle: JoinHandle,
+}
+
+/// What opening connections needs, shared between reconnects
+#[derive(Clone)]
+pub struct Connector {
+	pub config: ApiConfiguration,
+	pub credentials: Credentials,
+	pub rate_limiters: Arc<RateLimiters>,
+	pub middleware: Chain,
+	pub status: Status,
+	pub transport: Arc<dyn WsTransport>,
+}
+
+/// A single WS connection, which is replaced by a new one on reconnects
+struct Connection {
+	inner: Box<dyn WsConnection>,
+	middleware: Chain,
+	received_sender: broadcast::Sender<ReceivedFrame>,
+}
+
+impl Connection {
+	async fn open(
+		connector: &Connector, received_sender: broadcast::Sender<ReceivedFrame>,
 	) -> Result<Self, ApiError> {
 		use serde::ser::Error;
 
-		let mut headers = config.to_headers().map_err(|e| {
+		let mut headers = connector.config.to_headers().map_err(|e| {
 			serde_json::Error::custom(
 				"Couldn't parse config into headers: ".to_string() + &e.to_string(),
 			)
 		})?;
-		headers.append(&mut auth.to_headers().map_err(|e| {
-			serde_json::Error::custom(
-				"Couldn't parse auth into headers: ".to_string() + &e.to_string(),
-			)
-		})?);
+		headers.append(&mut current(&connector.credentials).to_headers().map_err(
+			|e| {
+				serde_json::Error::custom(
+					"Couldn't parse auth into headers: ".to_string() + &e.to_string(),
+				)
+			},
+		)?);
+
+		let inner = connector
+			.transport
+			.connect(&connector.config.ws_url, &headers.into_iter().collect())
+			.await?;
+		Ok(Self {
+			inner,
+			middleware: connector.middleware.clone(),
+			received_sender,
+		})
+	}
 
-		let mut ws_config = ezsockets::ClientConfig::new(crate::API_V1_WS_URL);
-		for (header_name, header_value) in headers {
-			// TODO: Remove as_* once ezsockets updates the http to >1
-			ws_config =
-				ws_config.header(header_name.as_str(), header_value.as_bytes());
+	/// Forwards outgoing messages & received frames until either the connection
+	/// drops, the sending half of `outgoing` is dropped, or a reconnection is
+	/// requested.
+	///
+	/// Also marks the user as online every `keepalive` interval, if it's set.
+	async fn run(
+		mut self, outgoing: &mut UnboundedReceiver<Outgoing>, queue: &mut Queue,
+		keepalive: Option<Duration>,
+	) -> Stopped {
+		while let Some(message) = queue.pop() {
+			if let Err(err) = self.send(message.clone()).await {
+				queue.push_front(message);
+				return Stopped::Dropped(err);
+			}
 		}
 
-		let (received_sender, received_receiver) =
-			tokio::sync::mpsc::unbounded_channel::<WsListenItem>();
+		// The first keepalive is sent right away
+		let mut next_keepalive = keepalive.map(|_| Instant::now());
+		loop {
+			tokio::select! {
+				() = until(next_keepalive) => {
+					next_keepalive = keepalive.map(|period| Instant::now() + period);
+					let sent = match encode(Online) {
+						Ok(message) => self.send(message).await,
+						Err(err) => Err(err),
+					};
+					if let Err(err) = sent {
+						return Stopped::Dropped(err);
+					}
+				}
+				message = outgoing.recv() => {
+					match message {
+						Some(Outgoing::Frame(message)) => {
+							if let Err(err) = self.send(message.clone()).await {
+								queue.push_front(message);
+								return Stopped::Dropped(err);
+							}
+						}
+						Some(Outgoing::Reconnect) => {
+							self.inner.close().await;
+							return Stopped::Reconnect;
+						}
+						None => {
+							self.inner.close().await;
+							return Stopped::Closed;
+						}
+					}
+				}
+				frame = self.inner.receive() => {
+					match frame {
+						Some(Ok(frame)) => self.received(frame.as_bytes()),
+						Some(Err(err)) => return Stopped::Dropped(err),
+						None => {
+							return Stopped::Dropped(ApiError::WebSocket(
+								"WS connection closed by the server".into(),
+							));
+						}
+					}
+				}
+			}
+		}
+	}
 
-		let (internal_client, future) = ezsockets::connect(
-			|_client| InternalClientExt { received_sender },
-			ws_config,
-		)
-		.await;
+	async fn send(&mut self, message: Vec<u8>) -> Result<(), ApiError> {
+		self.middleware.on_ws_send(&message);
+		self.inner.send(WsFrame::Binary(message)).await
+	}
+
+	/// Passes a received WS message on to the listeners
+	fn received(&self, bytes: &[u8]) {
+		self.middleware.on_ws_receive(bytes);
+		// No listeners is fine, the message is just dropped
+		self.received_sender.send(Ok(bytes.into())).ok();
+	}
+}
+
+/// A copy of the current credentials
+fn current(credentials: &Credentials) -> SavedLoginCredentials {
+	credentials.read().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+}
+
+/// Waits until the deadline, or forever if there isn't one
+async fn until(deadline: Option<Instant>) {
+	match deadline {
+		Some(deadline) => {
+			rt::sleep(deadline.saturating_duration_since(Instant::now())).await;
+		}
+		None => std::future::pending().await,
+	}
+}
+
+/// Runs the future while queueing the messages that are sent meanwhile
+///
+/// Returns `None` if the client was dropped before the future completed.
+async fn queueing<T>(
+	future: impl Future<Output = T>, outgoing: &mut UnboundedReceiver<Outgoing>,
+	queue: &mut Queue,
+) -> Option<T> {
+	tokio::pin!(future);
+	loop {
+		tokio::select! {
+			output = &mut future => return Some(output),
+			message = outgoing.recv() => match message? {
+				Outgoing::Frame(message) => queue.push(message),
+				// Already reconnecting, with the current credentials
+				Outgoing::Reconnect => {}
+			}
+		}
+	}
+}
 
-		let handle = tokio::spawn(async move {
-			future.await.unwrap();
-		});
+/// Serializes a WS message into the format that the CVR API expects
+fn encode(
+	requestable: impl Requestable + Serialize + Send,
+) -> Result<Vec<u8>, ApiError> {
+	let data = crate::query::RequestWrapper {
+		request_type: requestable.request_type(),
+		data: requestable,
+	};
+	Ok(serde_json::to_vec(&data)?)
+}
+
+impl Client {
+	pub async fn new(connector: Connector) -> Result<Self, ApiError> {
+		let (received_sender, first_listener) = broadcast::channel::<ReceivedFrame>(
+			connector.config.ws_listener_capacity.max(1),
+		);
+		let (outgoing_sender, outgoing_receiver) =
+			tokio::sync::mpsc::unbounded_channel::<Outgoing>();
+
+		connector.status.set(ConnectionState::Connecting);
+		connector.rate_limiters.until_ready(Route::WsConnect).await;
+		let connection = Connection::open(&connector, received_sender.clone())
+			.await
+			.inspect_err(|err| connector.status.disconnected(err))?;
+		connector.status.set(ConnectionState::Connected);
 
-		// TODO: See if this can be removed?
-		internal_client.call(()).ok();
+		let received = received_sender.clone();
+		let handle = rt::spawn(Self::supervise(
+			connection,
+			connector,
+			received_sender,
+			outgoing_receiver,
+		));
 
 		let ws_client = Self {
-			internal: internal_client,
+			outgoing: outgoing_sender,
 			handle,
-			receive: std::sync::Arc::new(tokio::sync::Mutex::new(
-				UnboundedReceiverStream::from(received_receiver),
-			)),
+			received,
+			first_listener: std::sync::Mutex::new(Some(first_listener)),
 		};
 
 		Ok(ws_client)
 	}
 
-	/// Sends a WS message to the CVR API.
+	/// Keeps the connection alive, reconnecting as per the configured
+	/// [`ReconnectPolicy`] whenever it drops.
+	async fn supervise(
+		mut connection: Connection, connector: Connector,
+		received_sender: broadcast::Sender<ReceivedFrame>,
+		mut outgoing: UnboundedReceiver<Outgoing>,
+	) {
+		let Connector { config, rate_limiters, middleware, status, .. } =
+			&connector;
+		let policy = &config.ws_reconnect;
+		let mut queue = Queue::new(config.ws_queue.clone());
+		loop {
+			// Requested reconnections are attempted once right away, without
+			// counting as a failure
+			let mut reason = match connection
+				.run(&mut outgoing, &mut queue, config.ws_keepalive_interval)
+				.await
+			{
+				Stopped::Closed => {
+					status.set(ConnectionState::Closed);
+					return;
+				}
+				Stopped::Reconnect => {
+					status.set(ConnectionState::Connecting);
+					None
+				}
+				Stopped::Dropped(reason) => {
+					middleware.on_error(&reason);
+					status.disconnected(&reason);
+					Some(reason)
+				}
+			};
+
+			let mut attempt = 0;
+			connection = loop {
+				if let Some(reason) = &reason {
+					attempt += 1;
+					if !policy.should_retry(attempt) {
+						// Let the listeners know why the stream is ending
+						received_sender.send(Err(reason.to_string().into())).ok();
+						return;
+					}
+					status.set(ConnectionState::Reconnecting { attempt });
+					let delay = rt::sleep(policy.delay(attempt));
+					if queueing(delay, &mut outgoing, &mut queue).await.is_none() {
+						return;
+					}
+				}
+				let open = async {
+					rate_limiters.until_ready(Route::WsConnect).await;
+					Connection::open(&connector, received_sender.clone()).await
+				};
+				match queueing(open, &mut outgoing, &mut queue).await {
+					Some(Ok(connection)) => {
+						status.set(ConnectionState::Connected);
+						break connection;
+					}
+					Some(Err(err)) => {
+						middleware.on_error(&err);
+						status.disconnected(&err);
+						reason = Some(err);
+					}
+					None => return,
+				}
+			};
+		}
+	}
+
+	/// If the client has stopped for good, after giving up on reconnecting
+	pub fn is_finished(&self) -> bool { self.outgoing.is_closed() }
+
+	/// Reconnects right away, for example to use new credentials
+	pub fn reconnect(&self) { self.outgoing.send(Outgoing::Reconnect).ok(); }
+
+	/// Sends a WS message to the CVR API, or queues it if the connection is
+	/// being re-established.
 	///
 	/// # Errors
 	///
 	/// If something with the request failed.
 	pub fn send(
-		&self, requestable: impl crate::query::Requestable + Serialize + Send,
+		&self, requestable: impl Requestable + Serialize + Send,
 	) -> Result<(), ApiError> {
-		let data = crate::query::RequestWrapper {
-			request_type: requestable.request_type(),
-			data: requestable,
-		};
-		let data = serde_json::to_vec(&data)?;
-		self.internal.binary(data).map_err(|e| ApiError::WebSocket(Box::new(e)))?;
+		let data = encode(requestable)?;
+		self
+			.outgoing
+			.send(Outgoing::Frame(data))
+			.map_err(|_| ApiError::WebSocket("WS connection closed".into()))?;
 
 		Ok(())
 	}
 
-	pub fn listen(&self) -> ReceiverContainer { self.receive.clone() }
+	/// Creates a new listener, the first of which also gets the messages that
+	/// were received before it was created
+	pub fn listen(&self) -> WsListener {
+		let receiver = self
+			.first_listener
+			.lock()
+			.ok()
+			.and_then(|mut first_listener| first_listener.take())
+			.unwrap_or_else(|| self.received.subscribe());
+		WsListener { inner: BroadcastStream::new(receiver) }
+	}
+
+	/// Creates a new listener that only gets the messages received from now on
+	pub fn subscribe(&self) -> WsListener {
+		WsListener { inner: BroadcastStream::new(self.received.subscribe()) }
+	}
 }
 
 impl Drop for Client {
@@ -42,7 +42,6 @@ macro_rules! add_id {
 		#[cfg(any(feature = "http", feature = "ws"))]
 		impl AsRef<str> for $name {
 			/// Extracts a string slice containing the entire inner String.
-			#[must_use]
 			fn as_ref(&self) -> &str {
 				&self.0
 			}
@@ -161,7 +160,6 @@ pub enum Any {
 #[cfg(any(feature = "http", feature = "ws"))]
 impl AsRef<str> for Any {
 	/// Extracts a string slice containing the entire inner String.
-	#[must_use]
 	fn as_ref(&self) -> &str {
 		match self {
 			Self::User(v) => v.as_ref(),
@@ -7,7 +7,7 @@
 //! It also definitions for the requests, using [`racal`](https://docs.rs/racal/latest/racal/) for the HTTP parts,
 //! and big request/response enums for `WebSocket`s,
 //! meaning that there's no lock-in to a single API client.
-//! An example API client is provided for convenience though using [`reqwest`](https://crates.io/crates/reqwest).
+//! An example API client is provided for convenience though using [`reqwest`](https://crates.io/crates/reqwest) by default.
 //!
 //! The API technically isn't public yet, so proceed with your own discretion.
 //! That also means there is no official API documentation.
@@ -44,5 +44,7 @@ pub mod id;
 pub mod model;
 pub mod query;
 
-#[cfg(any(feature = "http_client", feature = "ws_client"))]
+#[cfg(any(feature = "http_client_core", feature = "ws_client"))]
 pub mod api_client;
+#[cfg(feature = "mock")]
+pub mod mock;
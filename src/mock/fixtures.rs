@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+
+use time::OffsetDateTime;
+
+use crate::{
+	model::{
+		AssetBase,
+		AssetBaseWithCategories,
+		AssetBaseWithTags,
+		AssetTag,
+		Categories,
+		Category,
+		ExtendedInstanceDetails,
+		FeaturedItem,
+		FriendRequests,
+		Friends,
+		InstanceDetails,
+		InstancePrivacy,
+		InstanceRegion,
+		SearchResult,
+		SearchResultId,
+		UserAuth,
+		UserBase,
+		UserDetails,
+		WorldDetails,
+		WorldDetailsResponse,
+		WorldInstance,
+		WorldListItem,
+	},
+	query::LoginCredentials,
+};
+
+/// An account that can be logged into on the mock server
+#[derive(Debug, Clone)]
+pub struct MockAccount {
+	/// The credentials to login with
+	pub credentials: LoginCredentials,
+	/// What the login responds with, the access key of which authenticates
+	/// other requests
+	pub auth: UserAuth,
+}
+
+/// The data that the mock server serves
+///
+/// The [`Default`] implementation has a bit of everything, to make every query
+/// return something.
+#[derive(Debug, Clone)]
+pub struct Fixtures {
+	/// The accounts that can be logged into
+	pub accounts: Vec<MockAccount>,
+	/// The users that can be looked up
+	pub users: Vec<UserDetails>,
+	/// The friends list of every account
+	pub friends: Friends,
+	/// The friend requests of every account
+	pub friend_requests: FriendRequests,
+	/// The worlds that can be looked up
+	pub worlds: Vec<WorldDetailsResponse>,
+	/// The world lists, keyed by their category's ID
+	pub world_lists: HashMap<String, Vec<WorldListItem>>,
+	/// The instances that can be looked up
+	pub instances: Vec<ExtendedInstanceDetails>,
+	/// Everything that can be searched for, matched by name
+	pub search_results: Vec<SearchResult>,
+	/// The categories of every account
+	pub categories: Categories,
+}
+
+impl Fixtures {
+	/// Finds the account that an username and access key belong to
+	#[must_use]
+	pub fn account(
+		&self, username: &str, access_key: &str,
+	) -> Option<&MockAccount> {
+		self.accounts.iter().find(|account| {
+			account.auth.username == username && account.auth.access_key == access_key
+		})
+	}
+}
+
+/// Turns a known good string into an ID
+fn id<Id: TryFrom<String>>(id: impl Into<String>) -> Id {
+	Id::try_from(id.into())
+		.unwrap_or_else(|_| unreachable!("IDs accept any string"))
+}
+
+fn user(id_str: &str, name: &str) -> UserBase {
+	UserBase {
+		id: id(id_str),
+		name: name.to_owned(),
+		image_url: format!("https://files.abidata.io/user_images/{id_str}.png"),
+	}
+}
+
+fn asset(id_str: &str, name: &str) -> AssetBase {
+	AssetBase {
+		id: id(id_str),
+		name: name.to_owned(),
+		image_url: format!("https://files.abidata.io/user_content/{id_str}.png"),
+	}
+}
+
+fn category(id_str: &str, name: &str) -> Category {
+	Category { id: id(id_str), name: name.to_owned() }
+}
+
+fn world(id_str: &str, name: &str, author: &UserBase) -> WorldDetailsResponse {
+	let uploaded_at = OffsetDateTime::UNIX_EPOCH + time::Duration::days(19_000);
+	WorldDetailsResponse {
+		instances: vec![WorldInstance {
+			id: id("i+a08c7c940906f17d-829305-fd561f-171faa79"),
+			name: format!("{name} (#417050)"),
+			player_count: 7,
+			max_player_count: 30,
+			region: InstanceRegion::Europe,
+		}],
+		world: WorldDetails {
+			base: AssetBaseWithTags {
+				base: asset(id_str, name),
+				tags: vec![AssetTag::LoudAudio, AssetTag::FlashingLights],
+			},
+			description: format!("The {name} world"),
+			user: author.clone(),
+			uploaded_at,
+			updated_at: uploaded_at + time::Duration::days(100),
+			switch_permitted: true,
+			is_published: true,
+			categories: vec!["wrldactive".to_owned()],
+			filesize: 123_456_789,
+		},
+	}
+}
+
+impl Default for Fixtures {
+	// It's just a lot of data
+	#[allow(clippy::too_many_lines)]
+	fn default() -> Self {
+		let owner = user("81c652f6-f2e9-6d48-fff9-1584fc6ac95d", "ljoonal");
+		let friend = user("6d8ea1b0-1e62-4bca-9b06-e3b2e47c9c7b", "Friendly");
+		let requester = user("f5c8d7a1-4e0b-4a55-8d2c-7b1b1d0f6c3e", "Stranger");
+		let avatar = asset("a4a2f4e0-2c69-4c5b-9d43-5e4f3a0c7b21", "Robot");
+		let club =
+			world("3b4e8d4c-3d5f-4a39-9b9a-0e3a5d55e8f1", "The Club", &owner);
+		let club_two =
+			world("9a7d1f52-6a3b-4e0e-8f4c-2d1c6b0a5e73", "Club Two", &friend);
+
+		let user_details = |base: &UserBase| UserDetails {
+			base: base.clone(),
+			rank: "User".to_owned(),
+			featured_badge: FeaturedItem {
+				name: "Closed Beta Tester".to_owned(),
+				image: "https://files.abidata.io/static_web/Badges/beta.png".to_owned(),
+			},
+			featured_group: FeaturedItem {
+				name: "Onlivfe".to_owned(),
+				image: "https://files.abidata.io/static_web/Groups/onlivfe.png"
+					.to_owned(),
+			},
+			avatar: avatar.clone(),
+		};
+
+		let instance = ExtendedInstanceDetails {
+			base: InstanceDetails {
+				id: id("i+a08c7c940906f17d-829305-fd561f-171faa79"),
+				name: "The Club (#417050)".to_owned(),
+				region: InstanceRegion::Europe,
+				game_mode_id: "SocialVR".to_owned(),
+				game_mode_name: "Social VR".to_owned(),
+				world: club.world.base.base.clone(),
+				max_players: 30,
+				current_player_count: 2,
+				members: vec![owner.clone(), friend.clone()],
+			},
+			instance_setting_privacy: InstancePrivacy::Public,
+			author: owner.clone(),
+			owner: user_details(&owner),
+			world: club.world.base.clone(),
+		};
+
+		let world_list_item = |world: &WorldDetailsResponse| WorldListItem {
+			base: world.world.base.base.clone(),
+			player_count: world.instances.iter().map(|i| i.player_count).sum(),
+		};
+
+		Self {
+			accounts: vec![MockAccount {
+				credentials: LoginCredentials {
+					email: "mock@example.com".to_owned(),
+					password: "hunter2".to_owned(),
+				},
+				auth: UserAuth {
+					username: owner.name.clone(),
+					access_key: "mock-access-key-that-is-long-enough".to_owned(),
+					user_id: owner.id.clone(),
+					current_avatar: avatar.id.clone(),
+					current_home_world: club.world.base.base.id.clone(),
+					video_url_resolver_executable:
+						"https://files.abidata.io/static_web/yt-dlp.exe".to_owned(),
+					video_url_resolver_hashes:
+						"https://files.abidata.io/static_web/yt-dlp.sha512".to_owned(),
+					blocked_users: vec![],
+				},
+			}],
+			users: vec![
+				user_details(&owner),
+				user_details(&friend),
+				user_details(&requester),
+			],
+			friends: Friends(vec![AssetBaseWithCategories {
+				base: AssetBase {
+					id: friend.id.clone(),
+					name: friend.name.clone(),
+					image_url: friend.image_url.clone(),
+				},
+				categories: vec!["frndbesties".to_owned()],
+			}]),
+			friend_requests: FriendRequests(vec![AssetBase {
+				id: id(requester.id.clone()),
+				name: requester.name.clone(),
+				image_url: requester.image_url.clone(),
+			}]),
+			world_lists: HashMap::from([(
+				"wrldactive".to_owned(),
+				vec![world_list_item(&club), world_list_item(&club_two)],
+			)]),
+			search_results: vec![
+				SearchResult {
+					id: SearchResultId::World(club.world.base.base.id.clone()),
+					name: club.world.base.base.name.clone(),
+					image_url: club.world.base.base.image_url.clone(),
+				},
+				SearchResult {
+					id: SearchResultId::World(club_two.world.base.base.id.clone()),
+					name: club_two.world.base.base.name.clone(),
+					image_url: club_two.world.base.base.image_url.clone(),
+				},
+				SearchResult {
+					id: SearchResultId::User(owner.id.clone()),
+					name: owner.name.clone(),
+					image_url: owner.image_url.clone(),
+				},
+				SearchResult {
+					id: SearchResultId::Avatar(avatar.id.clone()),
+					name: avatar.name.clone(),
+					image_url: avatar.image_url.clone(),
+				},
+			],
+			categories: Categories {
+				avatars: vec![
+					category("avtrpublic", "Public"),
+					category("avtrshared", "Shared"),
+					category("avtrmine", "Mine"),
+				],
+				worlds: vec![
+					category("wrldactive", "Active"),
+					category("wrldnew", "New"),
+					category("wrldtrending", "Trending"),
+					category("wrldrecentlyupdated", "Recently Updated"),
+					category("wrldavatars", "Avatar Worlds"),
+					category("wrldpublic", "Public"),
+					category("wrldmine", "Mine"),
+				],
+				friends: vec![
+					category("frndonline", "Online"),
+					category("frndbesties", "Besties"),
+				],
+				spawnables: vec![
+					category("propmine", "Mine"),
+					category("propshared", "Shared"),
+				],
+			},
+			instances: vec![instance],
+			worlds: vec![club, club_two],
+		}
+	}
+}
@@ -0,0 +1,550 @@
+//! A local stand-in for the CVR API, for testing without network access.
+//!
+//! The mock server serves the HTTP routes of the [`query`](crate::query)
+//! module and the WS API, using [`Fixtures`] built from the
+//! [`model`](crate::model) types.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), chilloutvr::api_client::ApiError> {
+//! use chilloutvr::{
+//! 	api_client::AuthenticatedCVR,
+//! 	mock::{Fixtures, MockServer},
+//! };
+//!
+//! let server = MockServer::start(Fixtures::default()).unwrap();
+//! let client = AuthenticatedCVR::new(
+//! 	server.configuration("example".to_owned()),
+//! 	server.fixtures().accounts[0].auth.clone(),
+//! )?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+	collections::{HashMap, VecDeque},
+	net::SocketAddr,
+	sync::{Arc, Mutex, MutexGuard},
+};
+
+use futures_util::SinkExt;
+use serde::Serialize;
+use tokio::{
+	io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+	net::{TcpListener, TcpStream},
+	sync::mpsc::{UnboundedSender, unbounded_channel},
+	task::JoinHandle,
+};
+use tokio_stream::StreamExt;
+use tokio_tungstenite::{
+	WebSocketStream,
+	tungstenite::{Message, handshake::derive_accept_key, protocol::Role},
+};
+
+use crate::{
+	api_client::ApiConfiguration,
+	model::{Invites, MenuPopup, Notification, WsResponse, WsResponseData},
+	query::{AuthType, RequestType, RequestWrapper},
+};
+
+mod fixtures;
+pub use fixtures::*;
+
+/// An HTTP request that the mock server received
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedRequest {
+	/// The HTTP method of the request
+	pub method: String,
+	/// The path of the request, including the `/1` API version prefix
+	pub path: String,
+	/// The headers of the request, with lowercase names
+	pub headers: HashMap<String, String>,
+}
+
+#[derive(Debug)]
+struct State {
+	fixtures: Fixtures,
+	requests: Vec<RecordedRequest>,
+	ws_messages: Vec<serde_json::Value>,
+	ws_sessions: Vec<UnboundedSender<String>>,
+	failures: VecDeque<Failure>,
+}
+
+/// An error response that the server was told to respond with
+#[derive(Debug)]
+struct Failure {
+	status: u16,
+	retry_after: Option<String>,
+}
+
+/// A mock CVR API server, listening on a local port
+///
+/// The server runs on the tokio runtime it was started on, until
+/// [`MockServer::shutdown`] is called or the runtime is dropped.
+#[derive(Debug)]
+pub struct MockServer {
+	address: SocketAddr,
+	state: Arc<Mutex<State>>,
+	handle: JoinHandle<()>,
+}
+
+impl MockServer {
+	/// Starts a mock server on a random local port
+	///
+	/// # Errors
+	///
+	/// If binding to a local port fails.
+	///
+	/// # Panics
+	///
+	/// If called outside of a tokio runtime.
+	pub fn start(fixtures: Fixtures) -> std::io::Result<Self> {
+		let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+		listener.set_nonblocking(true)?;
+		let listener = TcpListener::from_std(listener)?;
+		let address = listener.local_addr()?;
+		let state = Arc::new(Mutex::new(State {
+			fixtures,
+			requests: Vec::new(),
+			ws_messages: Vec::new(),
+			ws_sessions: Vec::new(),
+			failures: VecDeque::new(),
+		}));
+
+		let handle = tokio::spawn(Self::accept(listener, state.clone()));
+
+		Ok(Self { address, state, handle })
+	}
+
+	/// The base URL of the mock HTTP API
+	#[must_use]
+	pub fn http_url(&self) -> String { format!("http://{}/1", self.address) }
+
+	/// The URL of the mock WS API
+	#[must_use]
+	pub fn ws_url(&self) -> String { format!("ws://{}/1/users/ws", self.address) }
+
+	/// An API client configuration that points to this mock server
+	#[must_use]
+	pub fn configuration(&self, user_agent: String) -> ApiConfiguration {
+		let mut config = ApiConfiguration::new(user_agent);
+		config.http_url = self.http_url();
+		config.ws_url = self.ws_url();
+		config
+	}
+
+	fn state(&self) -> MutexGuard<'_, State> {
+		self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+	}
+
+	/// A copy of the data that the server is serving
+	#[must_use]
+	pub fn fixtures(&self) -> Fixtures { self.state().fixtures.clone() }
+
+	/// Changes the data that the server is serving
+	pub fn update_fixtures(&self, update: impl FnOnce(&mut Fixtures)) {
+		update(&mut self.state().fixtures);
+	}
+
+	/// The HTTP requests that the server has received so far
+	#[must_use]
+	pub fn requests(&self) -> Vec<RecordedRequest> {
+		self.state().requests.clone()
+	}
+
+	/// The WS messages that the server has received so far
+	#[must_use]
+	pub fn ws_messages(&self) -> Vec<serde_json::Value> {
+		self.state().ws_messages.clone()
+	}
+
+	/// How many WS connections are currently open
+	#[must_use]
+	pub fn ws_connections(&self) -> usize {
+		let mut state = self.state();
+		state.ws_sessions.retain(|session| !session.is_closed());
+		state.ws_sessions.len()
+	}
+
+	/// Sends a WS message to every connected client
+	pub fn push(&self, message: &serde_json::Value) {
+		let message = message.to_string();
+		self
+			.state()
+			.ws_sessions
+			.retain(|session| session.send(message.clone()).is_ok());
+	}
+
+	/// Makes the next HTTP request that isn't a WS upgrade fail with the status
+	/// code, and optionally a `Retry-After` header
+	///
+	/// Calling this multiple times queues up multiple failures.
+	pub fn fail_next(&self, status: u16, retry_after: Option<&str>) {
+		self.state().failures.push_back(Failure {
+			status,
+			retry_after: retry_after.map(ToOwned::to_owned),
+		});
+	}
+
+	/// Drops every open WS connection, as if the network failed
+	pub fn drop_ws_connections(&self) { self.state().ws_sessions.clear(); }
+
+	/// Stops the server
+	pub fn shutdown(self) {
+		self.drop_ws_connections();
+		self.handle.abort();
+	}
+
+	async fn accept(listener: TcpListener, state: Arc<Mutex<State>>) {
+		while let Ok((stream, _)) = listener.accept().await {
+			tokio::spawn(Self::handle(stream, state.clone()));
+		}
+	}
+
+	async fn handle(
+		stream: TcpStream, state: Arc<Mutex<State>>,
+	) -> std::io::Result<()> {
+		let mut reader = BufReader::new(stream);
+
+		let mut request_line = String::new();
+		reader.read_line(&mut request_line).await?;
+		let mut request_line = request_line.split_whitespace();
+		let method = request_line.next().unwrap_or_default().to_owned();
+		let path = request_line.next().unwrap_or_default().to_owned();
+
+		let mut headers = HashMap::new();
+		loop {
+			let mut line = String::new();
+			if reader.read_line(&mut line).await? == 0 || line.trim().is_empty() {
+				break;
+			}
+			if let Some((name, value)) = line.split_once(':') {
+				headers.insert(name.trim().to_lowercase(), value.trim().to_owned());
+			}
+		}
+
+		let mut body = vec![
+			0;
+			headers
+				.get("content-length")
+				.and_then(|l| l.parse().ok())
+				.unwrap_or(0)
+		];
+		reader.read_exact(&mut body).await?;
+		let mut stream = reader.into_inner();
+
+		let request = RecordedRequest { method, path, headers };
+		let mut extra_headers = String::new();
+		let (status, response) = {
+			let mut state =
+				state.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+			state.requests.push(request.clone());
+			if request.path == "/1/users/ws" {
+				authenticate(&state.fixtures, &request)
+					.map_or_else(|err| err, |()| (101, Vec::new()))
+			} else if let Some(failure) = state.failures.pop_front() {
+				if let Some(retry_after) = failure.retry_after {
+					extra_headers = format!("Retry-After: {retry_after}\r\n");
+				}
+				error(failure.status, "Injected failure")
+			} else {
+				respond(&state.fixtures, &request, &body)
+			}
+		};
+
+		if status == 101 {
+			let key =
+				request.headers.get("sec-websocket-key").cloned().unwrap_or_default();
+			stream
+				.write_all(
+					format!(
+						"HTTP/1.1 101 Switching Protocols\r\nConnection: Upgrade\r\nUpgrade: websocket\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+						derive_accept_key(key.as_bytes())
+					)
+					.as_bytes(),
+				)
+				.await?;
+			let socket =
+				WebSocketStream::from_raw_socket(stream, Role::Server, None).await;
+			Self::handle_ws(socket, state).await;
+			return Ok(());
+		}
+
+		let reason = match status {
+			200 => "OK",
+			400 => "Bad Request",
+			401 => "Unauthorized",
+			404 => "Not Found",
+			429 => "Too Many Requests",
+			500 => "Internal Server Error",
+			503 => "Service Unavailable",
+			_ => "Unknown",
+		};
+		stream
+			.write_all(
+				format!(
+					"HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n{extra_headers}\r\n",
+					response.len()
+				)
+				.as_bytes(),
+			)
+			.await?;
+		stream.write_all(&response).await?;
+		stream.shutdown().await
+	}
+
+	async fn handle_ws(
+		mut socket: WebSocketStream<TcpStream>, state: Arc<Mutex<State>>,
+	) {
+		let (session, mut pushes) = unbounded_channel();
+		let online_friends = {
+			let mut state =
+				state.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+			state.ws_sessions.push(session);
+			update("", WsResponseData::OnlineFriends(state.fixtures.friends.clone()))
+		};
+
+		if socket.send(Message::text(online_friends)).await.is_err() {
+			return;
+		}
+
+		loop {
+			tokio::select! {
+				push = pushes.recv() => {
+					let Some(push) = push else {
+						// Dropped on purpose, so no closing handshake
+						return;
+					};
+					if socket.send(Message::text(push)).await.is_err() {
+						return;
+					}
+				}
+				message = socket.next() => {
+					let message = match message {
+						Some(Ok(Message::Text(text))) => serde_json::from_str(&text),
+						Some(Ok(Message::Binary(bytes))) => serde_json::from_slice(&bytes),
+						Some(Ok(_)) => continue,
+						Some(Err(_)) | None => return,
+					};
+					let Ok(message) = message else {
+						continue;
+					};
+					let reply = {
+						let mut state =
+							state.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+						let reply = reply(&mut state.fixtures, &message);
+						state.ws_messages.push(message);
+						reply
+					};
+					if let Some(reply) = reply {
+						if socket.send(Message::text(reply)).await.is_err() {
+							return;
+						}
+					}
+				}
+			}
+		}
+	}
+}
+
+/// Responds to the WS requests that the API responds to, updating the fixtures
+/// like the API would
+fn reply(
+	fixtures: &mut Fixtures, message: &serde_json::Value,
+) -> Option<String> {
+	/// The data of the requests that target an user
+	#[derive(serde::Deserialize)]
+	struct Target {
+		id: String,
+	}
+
+	let request =
+		serde_json::from_value::<RequestWrapper<Target>>(message.clone()).ok()?;
+	let id = request.data.id.as_str();
+	let user_exists =
+		fixtures.users.iter().any(|user| user.base.id.as_ref() == id);
+	let requested = fixtures
+		.friend_requests
+		.0
+		.iter()
+		.position(|request| request.id.as_ref() == id);
+
+	match (request.request_type, requested) {
+		(RequestType::FriendRequestSend | RequestType::InviteSend, _)
+			if !user_exists =>
+		{
+			Some(popup(&format!("User not found: {id}")))
+		}
+		(RequestType::FriendRequestSend, _) => Some(update(
+			"Friend request sent",
+			WsResponseData::FriendRequest(fixtures.friend_requests.clone()),
+		)),
+		(
+			RequestType::FriendRequestAccept | RequestType::FriendRequestDecline,
+			Some(index),
+		) => {
+			fixtures.friend_requests.0.remove(index);
+			Some(update(
+				"",
+				WsResponseData::FriendRequest(fixtures.friend_requests.clone()),
+			))
+		}
+		(
+			RequestType::FriendRequestAccept | RequestType::FriendRequestDecline,
+			None,
+		) => Some(popup(&format!("Friend request not found: {id}"))),
+		(RequestType::InviteSend, _) => {
+			Some(update("Invite sent", WsResponseData::Invites(Invites(Vec::new()))))
+		}
+		_ => None,
+	}
+}
+
+fn update(message: &str, data: WsResponseData) -> String {
+	let response = WsResponse { message: message.to_owned(), data };
+	serde_json::to_string(&response)
+		.unwrap_or_else(|_| unreachable!("WS responses are valid JSON"))
+}
+
+fn popup(message: &str) -> String {
+	update(
+		message,
+		WsResponseData::MenuPopup(Notification::Parsed(MenuPopup {
+			title: None,
+			message: message.to_owned(),
+			category: None,
+			timestamp: None,
+			action: None,
+		})),
+	)
+}
+
+/// Checks that the request has the headers of a known account
+fn authenticate(
+	fixtures: &Fixtures, request: &RecordedRequest,
+) -> Result<(), (u16, Vec<u8>)> {
+	let username = request.headers.get("username").map_or("", String::as_str);
+	let access_key = request.headers.get("accesskey").map_or("", String::as_str);
+	if fixtures.account(username, access_key).is_some() {
+		Ok(())
+	} else {
+		Err(error(401, "Invalid username or access key"))
+	}
+}
+
+fn error(status: u16, message: &str) -> (u16, Vec<u8>) {
+	(
+		status,
+		serde_json::json!({ "message": message, "data": null })
+			.to_string()
+			.into_bytes(),
+	)
+}
+
+fn data(message: &str, data: &impl Serialize) -> (u16, Vec<u8>) {
+	serde_json::to_vec(&serde_json::json!({ "message": message, "data": data }))
+		.map_or_else(
+			|_| error(500, "Couldn't serialize the response"),
+			|body| (200, body),
+		)
+}
+
+/// Decodes the `%XX` escapes of an URL path segment
+fn percent_decode(segment: &str) -> String {
+	let mut bytes = Vec::with_capacity(segment.len());
+	let mut rest = segment.as_bytes();
+	while let Some((&byte, tail)) = rest.split_first() {
+		rest = tail;
+		if byte == b'%' && tail.len() >= 2 {
+			if let Ok(decoded) =
+				u8::from_str_radix(&String::from_utf8_lossy(&tail[..2]), 16)
+			{
+				bytes.push(decoded);
+				rest = &tail[2..];
+				continue;
+			}
+		}
+		bytes.push(byte);
+	}
+	String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Responds to the HTTP routes that the [`query`](crate::query) module has
+fn respond(
+	fixtures: &Fixtures, request: &RecordedRequest, body: &[u8],
+) -> (u16, Vec<u8>) {
+	let path = request.path.split('?').next().unwrap_or_default();
+	let Some(path) = path.strip_prefix("/1/") else {
+		return error(404, "Not found");
+	};
+	let segments: Vec<String> = path.split('/').map(percent_decode).collect();
+	let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+	match (request.method.as_str(), segments.as_slice()) {
+		("POST", ["users", "auth"]) => {
+			let Ok(auth) = serde_json::from_slice::<AuthType>(body) else {
+				return error(400, "Invalid authentication request");
+			};
+			let account = match &auth {
+				AuthType::LoginProfile(credentials) => fixtures
+					.accounts
+					.iter()
+					.find(|account| &account.credentials == credentials),
+				AuthType::LoginCredentials(saved) => {
+					fixtures.account(&saved.username, &saved.access_key)
+				}
+			};
+			account.map_or_else(
+				|| error(401, "Invalid credentials"),
+				|account| {
+					data(
+						&format!("Successfully logged in as {}", account.auth.username),
+						&account.auth,
+					)
+				},
+			)
+		}
+		("GET", ["users", user_id]) => fixtures
+			.users
+			.iter()
+			.find(|user| user.base.id.as_ref() == *user_id)
+			.map_or_else(|| error(404, "User not found"), |user| data("", user)),
+		("GET", ["worlds", "list", category]) => fixtures
+			.world_lists
+			.get(*category)
+			.map_or_else(|| error(404, "Category not found"), |list| data("", list)),
+		("GET", ["worlds", world_id]) => fixtures
+			.worlds
+			.iter()
+			.find(|world| world.world.base.base.id.as_ref() == *world_id)
+			.map_or_else(|| error(404, "World not found"), |world| data("", world)),
+		("GET", ["instances", instance_id]) => fixtures
+			.instances
+			.iter()
+			.find(|instance| instance.base.id.as_ref() == *instance_id)
+			.map_or_else(
+				|| error(404, "Instance not found"),
+				|instance| data("", instance),
+			),
+		("GET", ["friends" | "search" | "categories", ..]) => {
+			if let Err(err) = authenticate(fixtures, request) {
+				return err;
+			}
+			match segments.as_slice() {
+				["friends"] => data("", &fixtures.friends),
+				["friends", "requests"] => data("", &fixtures.friend_requests),
+				["categories"] => data("", &fixtures.categories),
+				["search", term] => {
+					let term = term.to_lowercase();
+					let results: Vec<_> = fixtures
+						.search_results
+						.iter()
+						.filter(|result| result.name.to_lowercase().contains(&term))
+						.collect();
+					data("", &results)
+				}
+				_ => error(404, "Not found"),
+			}
+		}
+		_ => error(404, "Not found"),
+	}
+}
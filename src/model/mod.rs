@@ -27,6 +27,9 @@ pub use searches::*;
 mod categories;
 pub use categories::*;
 
+mod notifications;
+pub use notifications::*;
+
 /// Seems like a lot if not all of the API calls are wrapped
 /// in a generic data/message struct.
 #[cfg(feature = "http")]
@@ -57,12 +60,12 @@ pub struct ResponseDataWrapper<T> {
 #[non_exhaustive]
 /// The actual response data of an incoming WebSocket message
 pub enum WsResponseData {
-	/// Some sorta alert/notification most likely?
-	MenuPopup(serde_json::Value),
-	/// Some sorta alert/notification most likely?
-	HudMessage(serde_json::Value),
-	/// Some sorta alert/notification most likely?
-	PushNotification(serde_json::Value),
+	/// An alert that's shown as a popup in the menu
+	MenuPopup(Notification<MenuPopup>),
+	/// A short message that's shown on the HUD
+	HudMessage(Notification<HudMessage>),
+	/// A notification that's pushed to the user
+	PushNotification(Notification<PushNotification>),
 	/// Update of the status of online friends
 	OnlineFriends(Friends),
 	/// Update of current invites
@@ -71,9 +74,109 @@ pub enum WsResponseData {
 	RequestInvites(InviteRequest),
 	/// Update of current friend requests
 	FriendRequest(FriendRequests),
+	/// A response type that isn't supported yet, kept as is
+	///
+	/// Is an error instead with the `debug` feature.
+	Unknown {
+		/// The `responseType` of the message
+		response_type: u64,
+		/// The raw data of the message
+		data: serde_json::Value,
+	},
+}
+
+/// The type of a WS message going from the CVR server to the client
+#[cfg(feature = "ws")]
+#[derive(
+	Debug,
+	Clone,
+	Copy,
+	PartialEq,
+	Eq,
+	Hash,
+	strum::Display,
+	strum::AsRefStr,
+	strum::VariantNames,
+)]
+#[non_exhaustive]
+#[allow(missing_docs)]
+pub enum ResponseType {
+	MenuPopup = 0,
+	HudMessage = 1,
+	PushNotification = 2,
+	OnlineFriends = 10,
+	Invites = 15,
+	RequestInvites = 20,
+	FriendRequest = 25,
+}
+
+#[cfg(feature = "ws")]
+impl From<ResponseType> for u64 {
+	fn from(response_type: ResponseType) -> Self { response_type as Self }
+}
+
+#[cfg(feature = "ws")]
+impl TryFrom<u64> for ResponseType {
+	type Error = u64;
+
+	/// Fails with the code if it's not a known response type
+	fn try_from(code: u64) -> Result<Self, Self::Error> {
+		Ok(match code {
+			0 => Self::MenuPopup,
+			1 => Self::HudMessage,
+			2 => Self::PushNotification,
+			10 => Self::OnlineFriends,
+			15 => Self::Invites,
+			20 => Self::RequestInvites,
+			25 => Self::FriendRequest,
+			code => return Err(code),
+		})
+	}
+}
+
+#[cfg(feature = "ws")]
+impl Serialize for ResponseType {
+	fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+		s.serialize_u64((*self).into())
+	}
+}
+
+#[cfg(feature = "ws")]
+impl<'de> Deserialize<'de> for ResponseType {
+	fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+		let code = u64::deserialize(d)?;
+		Self::try_from(code).map_err(|code| {
+			D::Error::invalid_value(
+				serde::de::Unexpected::Unsigned(code),
+				&"a supported WS message responseType",
+			)
+		})
+	}
+}
+
+#[cfg(feature = "ws")]
+impl WsResponseData {
+	/// The `responseType` of the message
+	///
+	/// # Errors
+	///
+	/// With the raw code, if the response type is unknown.
+	pub const fn response_type(&self) -> Result<ResponseType, u64> {
+		Ok(match self {
+			Self::MenuPopup(_) => ResponseType::MenuPopup,
+			Self::HudMessage(_) => ResponseType::HudMessage,
+			Self::PushNotification(_) => ResponseType::PushNotification,
+			Self::OnlineFriends(_) => ResponseType::OnlineFriends,
+			Self::Invites(_) => ResponseType::Invites,
+			Self::RequestInvites(_) => ResponseType::RequestInvites,
+			Self::FriendRequest(_) => ResponseType::FriendRequest,
+			Self::Unknown { response_type, .. } => return Err(*response_type),
+		})
+	}
 }
 
 // Auto derives don't seem to support this, see https://github.com/serde-rs/serde/issues/745
+#[cfg(feature = "ws")]
 impl<'de> serde::Deserialize<'de> for WsResponseData {
 	fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
 		use serde_json::Value;
@@ -90,43 +193,91 @@ impl<'de> serde::Deserialize<'de> for WsResponseData {
 			.ok_or_else(|| D::Error::missing_field("data"))?
 			.take();
 
-		Ok(match resp_type {
-			0 => Self::MenuPopup(data),
-			1 => Self::HudMessage(data),
-			2 => Self::PushNotification(data),
-			10 => Self::OnlineFriends(serde_json::from_value(data).map_err(|e| {
-				D::Error::custom(format!(
-					"deserializing OnlineFriends data failed: {e:?}"
-				))
-			})?),
-			15 => Self::Invites(serde_json::from_value(data).map_err(|e| {
-				D::Error::custom(format!("deserializing Invites data failed: {e:?}"))
-			})?),
-			20 => {
-				Self::RequestInvites(serde_json::from_value(data).map_err(|e| {
-					D::Error::custom(format!(
-						"deserializing RequestInvites data failed: {e:?}"
-					))
-				})?)
-			}
-			25 => Self::FriendRequest(serde_json::from_value(data).map_err(|e| {
-				D::Error::custom(format!(
-					"deserializing FriendRequest data failed: {e:?}"
-				))
-			})?),
-			type_ => {
+		let resp_type = match ResponseType::try_from(resp_type) {
+			Ok(resp_type) => resp_type,
+			#[cfg(feature = "debug")]
+			Err(type_) => {
 				return Err(D::Error::invalid_value(
 					serde::de::Unexpected::Unsigned(type_),
 					&"a supported WS message responseType",
 				));
 			}
+			#[cfg(not(feature = "debug"))]
+			Err(response_type) => return Ok(Self::Unknown { response_type, data }),
+		};
+		let parse_err = |e: serde_json::Error| {
+			D::Error::custom(format!("deserializing {resp_type} data failed: {e:?}"))
+		};
+
+		Ok(match resp_type {
+			ResponseType::MenuPopup => {
+				Self::MenuPopup(notification(data, resp_type.as_ref())?)
+			}
+			ResponseType::HudMessage => {
+				Self::HudMessage(notification(data, resp_type.as_ref())?)
+			}
+			ResponseType::PushNotification => {
+				Self::PushNotification(notification(data, resp_type.as_ref())?)
+			}
+			ResponseType::OnlineFriends => {
+				Self::OnlineFriends(serde_json::from_value(data).map_err(parse_err)?)
+			}
+			ResponseType::Invites => {
+				Self::Invites(serde_json::from_value(data).map_err(parse_err)?)
+			}
+			ResponseType::RequestInvites => {
+				Self::RequestInvites(serde_json::from_value(data).map_err(parse_err)?)
+			}
+			ResponseType::FriendRequest => {
+				Self::FriendRequest(serde_json::from_value(data).map_err(parse_err)?)
+			}
 		})
 	}
 }
 
+#[cfg(feature = "ws")]
+impl Serialize for WsResponseData {
+	fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+		use serde::ser::SerializeMap;
+		let mut map = s.serialize_map(Some(2))?;
+		match self.response_type() {
+			Ok(response_type) => {
+				map.serialize_entry("responseType", &response_type)?;
+			}
+			Err(code) => map.serialize_entry("responseType", &code)?,
+		}
+		match self {
+			Self::MenuPopup(data) => map.serialize_entry("data", data)?,
+			Self::HudMessage(data) => map.serialize_entry("data", data)?,
+			Self::PushNotification(data) => map.serialize_entry("data", data)?,
+			Self::OnlineFriends(data) => map.serialize_entry("data", data)?,
+			Self::Invites(data) => map.serialize_entry("data", data)?,
+			Self::RequestInvites(data) => map.serialize_entry("data", data)?,
+			Self::FriendRequest(data) => map.serialize_entry("data", data)?,
+			Self::Unknown { data, .. } => map.serialize_entry("data", data)?,
+		}
+		map.end()
+	}
+}
+
+/// Parses the data of a notification, falling back to the raw value unless
+/// the `debug` feature is enabled
+#[cfg(feature = "ws")]
+fn notification<T: serde::de::DeserializeOwned, E: Error>(
+	data: serde_json::Value, kind: &str,
+) -> Result<Notification<T>, E> {
+	match T::deserialize(&data) {
+		Ok(notification) => Ok(Notification::Parsed(notification)),
+		Err(e) if cfg!(feature = "debug") => {
+			Err(E::custom(format!("deserializing {kind} data failed: {e:?}")))
+		}
+		Err(_) => Ok(Notification::Raw(data)),
+	}
+}
+
 #[cfg(feature = "http")]
 #[serde_as]
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 /// A WebSocket response
 pub struct WsResponse {
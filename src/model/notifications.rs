@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "ws")]
+use time::OffsetDateTime;
+
+/// The data of a notification, or the raw value if it wasn't in the expected
+/// shape
+///
+/// No notifications have been captured from the API yet, so the shapes of
+/// them are speculative. Only the `message` is required, which seems to be
+/// the least that every notification would have.
+#[cfg(feature = "ws")]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Notification<T> {
+	/// The notification in the expected shape
+	Parsed(T),
+	/// The data as is, if it didn't match the expected shape
+	Raw(serde_json::Value),
+}
+
+#[cfg(feature = "ws")]
+impl<T> Notification<T> {
+	/// The parsed notification, if the data was in the expected shape
+	#[must_use]
+	pub const fn parsed(&self) -> Option<&T> {
+		match self {
+			Self::Parsed(notification) => Some(notification),
+			Self::Raw(_) => None,
+		}
+	}
+}
+
+/// An alert that's shown as a popup in the menu
+///
+/// The fields besides the message are guesses, as no popups have been
+/// captured from the API yet.
+#[cfg(feature = "ws")]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MenuPopup {
+	/// The headline of the popup
+	#[serde(default, alias = "headline")]
+	pub title: Option<String>,
+	/// The actual contents of the popup
+	pub message: String,
+	/// What kind of a popup this is
+	#[serde(default)]
+	pub category: Option<String>,
+	/// When the popup was sent
+	#[serde(default, with = "time::serde::rfc3339::option")]
+	pub timestamp: Option<OffsetDateTime>,
+	/// What the popup does when it's acted upon
+	#[serde(default)]
+	pub action: Option<serde_json::Value>,
+}
+
+/// A short message that's shown on the HUD
+///
+/// Speculative like [`MenuPopup`], only the message is known to be there.
+#[cfg(feature = "ws")]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HudMessage {
+	/// The headline of the message
+	#[serde(default, alias = "headline")]
+	pub title: Option<String>,
+	/// The actual contents of the message
+	pub message: String,
+	/// What kind of a message this is
+	#[serde(default)]
+	pub category: Option<String>,
+	/// When the message was sent
+	#[serde(default, with = "time::serde::rfc3339::option")]
+	pub timestamp: Option<OffsetDateTime>,
+}
+
+/// A notification that's pushed to the user
+///
+/// Speculative like [`MenuPopup`], with the category being called a type
+/// instead, as that's what push notifications usually call it.
+#[cfg(feature = "ws")]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PushNotification {
+	/// The headline of the notification
+	#[serde(default, alias = "headline")]
+	pub title: Option<String>,
+	/// The actual contents of the notification
+	pub message: String,
+	/// What kind of a notification this is
+	#[serde(default, alias = "type")]
+	pub kind: Option<String>,
+	/// When the notification was sent
+	#[serde(default, with = "time::serde::rfc3339::option")]
+	pub timestamp: Option<OffsetDateTime>,
+	/// What the notification does when it's acted upon
+	#[serde(default)]
+	pub action: Option<serde_json::Value>,
+}
@@ -3,7 +3,9 @@ use racal::Queryable;
 use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "ws")]
-use super::{RequestType, Requestable};
+use super::{Confirmable, RequestType, Requestable};
+#[cfg(feature = "ws")]
+use crate::model::WsResponseData;
 #[cfg(feature = "http")]
 use crate::{
 	model::{Friends, ResponseDataWrapper},
@@ -55,6 +57,17 @@ impl Requestable for FriendRequest {
 	fn request_type(&self) -> RequestType { RequestType::FriendRequestSend }
 }
 
+#[cfg(feature = "ws")]
+impl Confirmable for FriendRequest {
+	/// The update only includes received requests, so it can't tell whether
+	/// this one was sent
+	fn is_confirmed_by(&self, _update: &WsResponseData) -> bool { false }
+
+	fn is_confirmable(&self) -> bool { false }
+
+	fn target(&self) -> &str { self.id.as_ref() }
+}
+
 /// Accepts another user's request to be friends
 #[cfg(feature = "ws")]
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
@@ -69,6 +82,16 @@ impl Requestable for AcceptFriendRequest {
 	fn request_type(&self) -> RequestType { RequestType::FriendRequestAccept }
 }
 
+#[cfg(feature = "ws")]
+impl Confirmable for AcceptFriendRequest {
+	/// The friend requests that no longer include the accepted one
+	fn is_confirmed_by(&self, update: &WsResponseData) -> bool {
+		is_resolved(update, self.target())
+	}
+
+	fn target(&self) -> &str { self.id.as_ref() }
+}
+
 /// Decline another user's request to be friends
 #[cfg(feature = "ws")]
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
@@ -83,6 +106,26 @@ impl Requestable for DeclineFriendRequest {
 	fn request_type(&self) -> RequestType { RequestType::FriendRequestDecline }
 }
 
+#[cfg(feature = "ws")]
+impl Confirmable for DeclineFriendRequest {
+	/// The friend requests that no longer include the declined one
+	fn is_confirmed_by(&self, update: &WsResponseData) -> bool {
+		is_resolved(update, self.target())
+	}
+
+	fn target(&self) -> &str { self.id.as_ref() }
+}
+
+/// If the update is of friend requests that don't include the one with the ID
+#[cfg(feature = "ws")]
+fn is_resolved(update: &WsResponseData, id: &str) -> bool {
+	matches!(
+		update,
+		WsResponseData::FriendRequest(requests)
+			if requests.0.iter().all(|request| request.id.as_ref() != id)
+	)
+}
+
 /// Removes an user from the current user's friends list
 #[cfg(feature = "ws")]
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "ws")]
-use super::{RequestType, Requestable};
+use super::{Confirmable, RequestType, Requestable};
+#[cfg(feature = "ws")]
+use crate::model::WsResponseData;
 
 /// Invite an user to the currently logged in user's instance
 #[cfg(feature = "ws")]
@@ -17,6 +19,17 @@ impl Requestable for Invite {
 	fn request_type(&self) -> RequestType { RequestType::InviteSend }
 }
 
+#[cfg(feature = "ws")]
+impl Confirmable for Invite {
+	/// The update only includes received invites, so it can't tell whether
+	/// this one was sent
+	fn is_confirmed_by(&self, _update: &WsResponseData) -> bool { false }
+
+	fn is_confirmable(&self) -> bool { false }
+
+	fn target(&self) -> &str { self.id.as_ref() }
+}
+
 /// Requests an invite from an user
 #[cfg(feature = "ws")]
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
@@ -31,6 +44,19 @@ impl Requestable for InviteRequest {
 	fn request_type(&self) -> RequestType { RequestType::RequestInvite }
 }
 
+#[cfg(feature = "ws")]
+impl Confirmable for InviteRequest {
+	/// The invite request that was sent to the user
+	fn is_confirmed_by(&self, update: &WsResponseData) -> bool {
+		matches!(
+			update,
+			WsResponseData::RequestInvites(request) if request.receiver_id == self.id
+		)
+	}
+
+	fn target(&self) -> &str { self.id.as_ref() }
+}
+
 /// Accepts an invite request,
 /// giving the requester an invite to the current user's instance
 #[cfg(feature = "ws")]
@@ -46,6 +72,19 @@ impl Requestable for AcceptInviteRequest {
 	fn request_type(&self) -> RequestType { RequestType::RequestInviteAccept }
 }
 
+#[cfg(feature = "ws")]
+impl Confirmable for AcceptInviteRequest {
+	/// An update of the accepted invite request
+	fn is_confirmed_by(&self, update: &WsResponseData) -> bool {
+		matches!(
+			update,
+			WsResponseData::RequestInvites(request) if request.id == self.id
+		)
+	}
+
+	fn target(&self) -> &str { self.id.as_ref() }
+}
+
 /// Declines an invite request
 #[cfg(feature = "ws")]
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
@@ -60,6 +99,19 @@ impl Requestable for DeclineInviteRequest {
 	fn request_type(&self) -> RequestType { RequestType::RequestInviteDecline }
 }
 
+#[cfg(feature = "ws")]
+impl Confirmable for DeclineInviteRequest {
+	/// An update of the declined invite request
+	fn is_confirmed_by(&self, update: &WsResponseData) -> bool {
+		matches!(
+			update,
+			WsResponseData::RequestInvites(request) if request.id == self.id
+		)
+	}
+
+	fn target(&self) -> &str { self.id.as_ref() }
+}
+
 /// Marks an invite as expired
 #[cfg(feature = "ws")]
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
@@ -44,13 +44,13 @@ impl racal::FromApiState<Self> for SavedLoginCredentials {
 #[derive(
 	Debug,
 	Clone,
+	Copy,
 	PartialEq,
 	Eq,
 	Hash,
-	Deserialize,
-	Serialize,
 	strum::Display,
 	strum::AsRefStr,
+	strum::EnumString,
 	strum::VariantNames,
 )]
 #[non_exhaustive]
@@ -77,6 +77,95 @@ pub enum RequestType {
 	SystemUserAnnouncement = 111,
 }
 
+#[cfg(feature = "ws")]
+impl From<RequestType> for u64 {
+	fn from(request_type: RequestType) -> Self { request_type as Self }
+}
+
+#[cfg(feature = "ws")]
+impl TryFrom<u64> for RequestType {
+	type Error = u64;
+
+	/// Fails with the code if it's not a known request type
+	fn try_from(code: u64) -> Result<Self, Self::Error> {
+		Ok(match code {
+			0 => Self::SelfOnline,
+			5 => Self::FriendRequestSend,
+			6 => Self::FriendRequestAccept,
+			7 => Self::FriendRequestDecline,
+			8 => Self::UnFriend,
+			10 => Self::InviteSend,
+			11 => Self::InviteExpire,
+			15 => Self::RequestInvite,
+			16 => Self::RequestInviteAccept,
+			17 => Self::RequestInviteDecline,
+			30 => Self::BlockUser,
+			31 => Self::UnBlockUser,
+			100 => Self::GlobalMessage,
+			101 => Self::UserMessage,
+			110 => Self::SystemGlobalAnnouncement,
+			111 => Self::SystemUserAnnouncement,
+			code => return Err(code),
+		})
+	}
+}
+
+/// Serialized as the numeric code that the WS API uses
+#[cfg(feature = "ws")]
+impl Serialize for RequestType {
+	fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+		s.serialize_u64((*self).into())
+	}
+}
+
+/// Deserialized from either the numeric code or the name of the variant
+#[cfg(feature = "ws")]
+impl<'de> Deserialize<'de> for RequestType {
+	fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+		struct Visitor;
+
+		impl serde::de::Visitor<'_> for Visitor {
+			type Value = RequestType;
+
+			fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+				f.write_str("a WS message requestType code or name")
+			}
+
+			fn visit_u64<E: serde::de::Error>(
+				self, code: u64,
+			) -> Result<Self::Value, E> {
+				RequestType::try_from(code).map_err(|code| {
+					E::invalid_value(serde::de::Unexpected::Unsigned(code), &self)
+				})
+			}
+
+			fn visit_i64<E: serde::de::Error>(
+				self, code: i64,
+			) -> Result<Self::Value, E> {
+				match u64::try_from(code) {
+					Ok(code) => self.visit_u64(code),
+					Err(_) => {
+						Err(E::invalid_value(serde::de::Unexpected::Signed(code), &self))
+					}
+				}
+			}
+
+			fn visit_str<E: serde::de::Error>(
+				self, name: &str,
+			) -> Result<Self::Value, E> {
+				name.parse().map_err(|_| {
+					E::unknown_variant(
+						name,
+						<RequestType as strum::VariantNames>::VARIANTS,
+					)
+				})
+			}
+		}
+
+		d.deserialize_any(Visitor)
+	}
+}
+
 /// Data for a WS request
 #[cfg(feature = "ws")]
 pub trait Requestable {
@@ -84,6 +173,70 @@ pub trait Requestable {
 	fn request_type(&self) -> RequestType;
 }
 
+/// A WS request that the API responds to with an update, so that its outcome
+/// can be waited for
+#[cfg(feature = "ws")]
+pub trait Confirmable: Requestable {
+	/// If the update is the one that the API responds to this request with
+	fn is_confirmed_by(&self, update: &crate::model::WsResponseData) -> bool;
+
+	/// If the update that the API responds with can be told apart from ones
+	/// caused by other requests
+	///
+	/// If it can't, the outcome is only known if the request is rejected.
+	fn is_confirmable(&self) -> bool { true }
+
+	/// The ID of what the request is about
+	fn target(&self) -> &str;
+
+	/// If the popup or HUD message rejects this request
+	///
+	/// The API doesn't say which request a popup is about, so only ones that
+	/// mention the ID that the request is about in their message or action are
+	/// treated as rejections.
+	fn is_rejected_by(&self, update: &crate::model::WsResponseData) -> bool {
+		use crate::model::WsResponseData;
+
+		let target = self.target();
+		match update {
+			WsResponseData::MenuPopup(popup) => popup.parsed().is_some_and(|popup| {
+				mentions(&popup.message, target)
+					|| popup
+						.action
+						.as_ref()
+						.is_some_and(|action| refers_to(action, target))
+			}),
+			WsResponseData::HudMessage(message) => message
+				.parsed()
+				.is_some_and(|message| mentions(&message.message, target)),
+			_ => false,
+		}
+	}
+}
+
+/// If the ID is one of the words of the message
+#[cfg(feature = "ws")]
+fn mentions(message: &str, id: &str) -> bool {
+	message
+		.split(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+		.any(|word| word == id)
+}
+
+/// If any of the strings in the JSON value is the ID
+#[cfg(feature = "ws")]
+fn refers_to(value: &serde_json::Value, id: &str) -> bool {
+	match value {
+		serde_json::Value::String(value) => value == id,
+		serde_json::Value::Array(values) => {
+			values.iter().any(|value| refers_to(value, id))
+		}
+		serde_json::Value::Object(values) => {
+			values.values().any(|value| refers_to(value, id))
+		}
+		_ => false,
+	}
+}
+
 /// The WS API message base structure
 #[cfg(feature = "ws")]
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
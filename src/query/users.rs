@@ -76,6 +76,16 @@ impl From<UserAuth> for SavedLoginCredentials {
 	}
 }
 
+#[cfg(feature = "http")]
+impl From<&UserAuth> for SavedLoginCredentials {
+	fn from(auth: &UserAuth) -> Self {
+		Self {
+			access_key: auth.access_key.clone(),
+			username: auth.username.clone(),
+		}
+	}
+}
+
 impl From<&Self> for SavedLoginCredentials {
 	fn from(value: &Self) -> Self {
 		Self {
@@ -0,0 +1,68 @@
+#![cfg(all(feature = "blocking", feature = "mock"))]
+
+use chilloutvr::{
+	api_client::{
+		ApiError,
+		blocking::{AuthenticatedCVR, UnauthenticatedCVR},
+	},
+	mock::MockServer,
+	query::{FriendList, LoginCredentials, UserDetails},
+};
+mod common;
+
+/// Runs a mock server on a runtime of its own, as the blocking clients can't
+/// be used from within one
+fn mock_server() -> MockServer {
+	let (sender, receiver) = std::sync::mpsc::channel();
+	std::thread::spawn(move || {
+		tokio::runtime::Builder::new_current_thread()
+			.enable_all()
+			.build()
+			.unwrap()
+			.block_on(async {
+				sender.send(common::mock_server()).unwrap();
+				std::future::pending::<()>().await;
+			});
+	});
+	receiver.recv().unwrap()
+}
+
+#[test]
+fn login_and_query() {
+	let server = mock_server();
+	let account = server.fixtures().accounts[0].clone();
+	let api_client =
+		UnauthenticatedCVR::new(common::configuration(&server)).unwrap();
+
+	let (api_client, user_auth) = api_client.login(account.credentials).unwrap();
+	assert_eq!(user_auth, account.auth);
+
+	let friends = api_client.query(FriendList()).unwrap();
+	assert_eq!(friends.data, server.fixtures().friends);
+
+	let api_client = api_client.downgrade().unwrap();
+	let err = api_client
+		.login(LoginCredentials {
+			email: "nobody@example.com".to_owned(),
+			password: "hunter2".to_owned(),
+		})
+		.unwrap_err();
+	assert!(matches!(err, ApiError::InvalidCredentials(_)), "{err:?}");
+}
+
+#[test]
+fn authenticated() {
+	let server = mock_server();
+	let user = server.fixtures().users[1].clone();
+	let api_client = AuthenticatedCVR::new(
+		common::configuration(&server),
+		server.fixtures().accounts[0].auth.clone(),
+	)
+	.unwrap();
+
+	let details =
+		api_client.query(UserDetails { user_id: user.base.id.clone() }).unwrap();
+
+	assert_eq!(details.data, user);
+	assert_eq!(api_client.credentials(), *api_client.state());
+}
@@ -0,0 +1,69 @@
+#![cfg(feature = "mock")]
+
+use std::time::Duration;
+
+use chilloutvr::{
+	api_client::ResponseCache,
+	query::{Categories, FriendList, UserDetails},
+};
+mod common;
+
+fn user_details(server: &chilloutvr::mock::MockServer) -> UserDetails {
+	UserDetails { user_id: server.fixtures().users[0].base.id.clone() }
+}
+
+#[tokio::test]
+async fn caches_configured_queries() {
+	let server = common::mock_server();
+	let cache = ResponseCache::new(10)
+		.with_ttl::<UserDetails>(Duration::from_secs(60))
+		.with_ttl::<Categories>(Duration::from_secs(60));
+	let api_client = common::authenticated_client(&server).with_cache(cache);
+
+	let first = api_client.query(user_details(&server)).await.unwrap();
+	let second = api_client.query(user_details(&server)).await.unwrap();
+	api_client.query(Categories {}).await.unwrap();
+	api_client.query(Categories {}).await.unwrap();
+
+	assert_eq!(first.data.base.id, second.data.base.id);
+	assert_eq!(server.requests().len(), 2);
+
+	// Not configured to be cached
+	api_client.query(FriendList {}).await.unwrap();
+	api_client.query(FriendList {}).await.unwrap();
+	assert_eq!(server.requests().len(), 4);
+}
+
+#[tokio::test]
+async fn expires() {
+	let server = common::mock_server();
+	let cache =
+		ResponseCache::new(10).with_default_ttl(Duration::from_millis(100));
+	let api_client = common::authenticated_client(&server).with_cache(cache);
+
+	api_client.query(Categories {}).await.unwrap();
+	tokio::time::sleep(Duration::from_millis(150)).await;
+	api_client.query(Categories {}).await.unwrap();
+
+	assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn invalidation_and_size_limit() {
+	let server = common::mock_server();
+	let cache = ResponseCache::new(1).with_default_ttl(Duration::from_secs(60));
+	let api_client =
+		common::authenticated_client(&server).with_cache(cache.clone());
+
+	api_client.query(Categories {}).await.unwrap();
+	cache.invalidate::<Categories>();
+	assert!(cache.is_empty());
+	api_client.query(Categories {}).await.unwrap();
+	assert_eq!(server.requests().len(), 2);
+
+	// Evicts the categories
+	api_client.query(FriendList {}).await.unwrap();
+	assert_eq!(cache.len(), 1);
+	api_client.query(Categories {}).await.unwrap();
+	assert_eq!(server.requests().len(), 4);
+}
@@ -1,13 +1,10 @@
 #![cfg(feature = "http_client")]
 
-use chilloutvr::{
-	api_client::{ApiClient, ApiError},
-	model::Categories,
-};
+use chilloutvr::{api_client::ApiError, model::Categories};
 mod common;
 
 #[tokio::test]
-#[ignore]
+#[cfg_attr(not(feature = "mock"), ignore)]
 async fn categories() -> Result<(), ApiError> {
 	let api_client = common::api_client();
 
@@ -1,11 +1,19 @@
-#![cfg(any(feature = "http_client", feature = "ws_client"))]
+#![cfg(any(feature = "http_client_core", feature = "ws_client"))]
 // Something's funky with checking if these are used or not.
 #![allow(dead_code)]
-use chilloutvr::{
-	api_client::{ApiConfiguration, AuthenticatedCVR, UnauthenticatedCVR},
-	model::{ResponseDataWrapper, UserAuth},
-	query::SavedLoginCredentials,
+use chilloutvr::api_client::{
+	ApiConfiguration,
+	AuthenticatedCVR,
+	UnauthenticatedCVR,
 };
+#[cfg(feature = "ws_client")]
+use chilloutvr::api_client::{MemoryServer, MemoryTransport};
+#[cfg(feature = "mock")]
+use chilloutvr::mock::{Fixtures, MockServer};
+#[cfg(not(feature = "mock"))]
+use chilloutvr::model::{ResponseDataWrapper, UserAuth};
+use chilloutvr::query::SavedLoginCredentials;
+#[cfg(not(feature = "mock"))]
 use once_cell::sync::Lazy;
 
 const USER_AGENT: &str = concat!(
@@ -16,6 +24,7 @@ const USER_AGENT: &str = concat!(
 	") - tests",
 );
 
+#[cfg(not(feature = "mock"))]
 pub static USER_AUTH: Lazy<UserAuth> = Lazy::new(|| {
 	let user_auth: UserAuth =
 		serde_json::from_slice::<ResponseDataWrapper<UserAuth>>(
@@ -32,14 +41,96 @@ pub static USER_AUTH: Lazy<UserAuth> = Lazy::new(|| {
 	user_auth
 });
 
+#[cfg(not(feature = "mock"))]
 pub fn api_client() -> AuthenticatedCVR {
 	AuthenticatedCVR::new(
 		ApiConfiguration::new(USER_AGENT.to_owned()),
-		SavedLoginCredentials::from(&USER_AUTH.clone().into()),
+		SavedLoginCredentials::from(&*USER_AUTH),
 	)
 	.unwrap()
 }
 
+#[cfg(not(feature = "mock"))]
 pub fn unauthenticated_api_client() -> UnauthenticatedCVR {
 	UnauthenticatedCVR::new(ApiConfiguration::new(USER_AGENT.to_owned())).unwrap()
 }
+
+/// Starts a mock API server on the current test's runtime
+#[cfg(feature = "mock")]
+pub fn mock_server() -> MockServer {
+	MockServer::start(Fixtures::default()).unwrap()
+}
+
+#[cfg(feature = "mock")]
+pub fn configuration(server: &MockServer) -> ApiConfiguration {
+	server.configuration(USER_AGENT.to_owned())
+}
+
+#[cfg(feature = "mock")]
+pub fn authenticated_client(server: &MockServer) -> AuthenticatedCVR {
+	AuthenticatedCVR::new(
+		configuration(server),
+		server.fixtures().accounts[0].auth.clone(),
+	)
+	.unwrap()
+}
+
+#[cfg(feature = "mock")]
+pub fn unauthenticated_client(server: &MockServer) -> UnauthenticatedCVR {
+	UnauthenticatedCVR::new(configuration(server)).unwrap()
+}
+
+#[cfg(feature = "mock")]
+pub fn api_client() -> AuthenticatedCVR { authenticated_client(&mock_server()) }
+
+#[cfg(feature = "mock")]
+pub fn unauthenticated_api_client() -> UnauthenticatedCVR {
+	unauthenticated_client(&mock_server())
+}
+
+/// An authenticated client of the mock server, with the configuration changed
+#[cfg(feature = "mock")]
+pub fn client_with(
+	server: &MockServer, configure: impl FnOnce(&mut ApiConfiguration),
+) -> AuthenticatedCVR {
+	let mut config = configuration(server);
+	configure(&mut config);
+	AuthenticatedCVR::new(config, server.fixtures().accounts[0].auth.clone())
+		.unwrap()
+}
+
+/// Makes reconnects quick, and the rate limits loose enough for them
+#[cfg(feature = "ws_client")]
+pub fn fast_reconnects(config: &mut ApiConfiguration) {
+	use std::{num::NonZeroU32, time::Duration};
+
+	config.ws_reconnect.initial_delay = Duration::from_millis(20);
+	config.ws_reconnect.max_delay = Duration::from_millis(20);
+	let unlimited = NonZeroU32::new(10_000).unwrap();
+	config.rate_limits.default =
+		chilloutvr::api_client::RateLimitQuota::new(unlimited, unlimited);
+}
+
+/// Credentials for clients that don't talk to the actual API
+pub fn fake_credentials() -> SavedLoginCredentials {
+	SavedLoginCredentials {
+		username: "ljoonal".to_owned(),
+		access_key: "an-access-key-that-is-long-enough".to_owned(),
+	}
+}
+
+/// A client that connects to the returned in-memory WS server, with the
+/// configuration changed
+#[cfg(feature = "ws_client")]
+pub fn memory_client(
+	configure: impl FnOnce(&mut ApiConfiguration),
+) -> (AuthenticatedCVR, MemoryServer) {
+	let mut config = ApiConfiguration::new(USER_AGENT.to_owned());
+	fast_reconnects(&mut config);
+	configure(&mut config);
+	let (transport, server) = MemoryTransport::new();
+	let api_client = AuthenticatedCVR::new(config, fake_credentials())
+		.unwrap()
+		.with_ws_transport(std::sync::Arc::new(transport));
+	(api_client, server)
+}
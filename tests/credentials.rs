@@ -0,0 +1,127 @@
+#![cfg(feature = "mock")]
+
+use std::sync::Arc;
+
+use chilloutvr::{
+	api_client::{
+		AuthenticatedCVR,
+		CredentialStore,
+		FileCredentialStore,
+		MemoryCredentialStore,
+	},
+	query::{FriendList, SavedLoginCredentials},
+};
+mod common;
+
+fn credentials(username: &str) -> SavedLoginCredentials {
+	SavedLoginCredentials {
+		username: username.to_owned(),
+		access_key: format!("{username}-access-key"),
+	}
+}
+
+#[test]
+fn file_store() -> std::io::Result<()> {
+	let path = std::env::temp_dir()
+		.join(format!("chilloutvr-credentials-{}.json", std::process::id()));
+	let store = FileCredentialStore::new(&path);
+
+	assert_eq!(store.load("main")?, None);
+	store.save("main", &credentials("main"))?;
+	store.save("alt", &credentials("alt"))?;
+	store.remove("nonexistent")?;
+
+	let reopened = FileCredentialStore::new(&path);
+	assert_eq!(reopened.load("main")?, Some(credentials("main")));
+	assert_eq!(reopened.accounts()?, ["alt", "main"]);
+	reopened.remove("alt")?;
+	assert_eq!(store.accounts()?, ["main"]);
+
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::PermissionsExt;
+		let mode = std::fs::metadata(&path)?.permissions().mode();
+		assert_eq!(mode & 0o777, 0o600);
+	}
+
+	std::fs::remove_file(path)
+}
+
+#[cfg(unix)]
+#[test]
+fn file_store_leftover_temp_file() -> std::io::Result<()> {
+	use std::os::unix::fs::PermissionsExt;
+
+	let path = std::env::temp_dir()
+		.join(format!("chilloutvr-leftover-{}.json", std::process::id()));
+	let temp_path = path.with_extension("json.tmp");
+	std::fs::write(&temp_path, "")?;
+	std::fs::set_permissions(&temp_path, std::fs::Permissions::from_mode(0o644))?;
+
+	FileCredentialStore::new(&path).save("main", &credentials("main"))?;
+
+	let mode = std::fs::metadata(&path)?.permissions().mode();
+	assert_eq!(mode & 0o777, 0o600);
+	std::fs::remove_file(path)
+}
+
+#[test]
+fn file_store_login_response() -> std::io::Result<()> {
+	let path = std::env::temp_dir()
+		.join(format!("chilloutvr-user-auth-{}.json", std::process::id()));
+	std::fs::write(
+		&path,
+		r#"{
+			"message": "Successfully logged in as ljoonal",
+			"data": {
+				"username": "ljoonal",
+				"accessKey": "ljoonal-access-key",
+				"userId": "uuid"
+			}
+		}"#,
+	)?;
+	let store = FileCredentialStore::new(&path);
+
+	assert_eq!(store.load("ljoonal")?, Some(credentials("ljoonal")));
+	store.save("alt", &credentials("alt"))?;
+	assert_eq!(FileCredentialStore::new(&path).accounts()?, ["alt", "ljoonal"]);
+
+	std::fs::remove_file(path)
+}
+
+#[tokio::test]
+async fn login_saves_and_loads() {
+	let server = common::mock_server();
+	let store = Arc::new(MemoryCredentialStore::default());
+	let account = server.fixtures().accounts[0].clone();
+
+	common::unauthenticated_client(&server)
+		.with_credential_store(store.clone(), "main")
+		.login(account.credentials)
+		.await
+		.unwrap();
+	assert_eq!(
+		store.load("main").unwrap(),
+		Some(SavedLoginCredentials::from(&account.auth))
+	);
+
+	let api_client =
+		AuthenticatedCVR::from_store(common::configuration(&server), store, "main")
+			.unwrap();
+	api_client.query(FriendList {}).await.unwrap();
+}
+
+#[test]
+fn missing_account() {
+	let store = Arc::new(MemoryCredentialStore::default());
+	let config = chilloutvr::api_client::ApiConfiguration::new("test".to_owned());
+
+	let Err(err) = AuthenticatedCVR::from_store(config, store, "missing") else {
+		panic!("loading a missing account should fail");
+	};
+	assert!(matches!(
+		err,
+		chilloutvr::api_client::ApiError::CredentialStore(ref e)
+			if e.kind() == std::io::ErrorKind::NotFound
+	));
+}
@@ -0,0 +1,44 @@
+#![cfg(feature = "mock")]
+
+use chilloutvr::{
+	api_client::{ApiError, AuthenticatedCVR},
+	id,
+	query::SavedLoginCredentials,
+};
+mod common;
+
+#[tokio::test]
+async fn not_found() {
+	let api_client = common::api_client();
+
+	let query = chilloutvr::query::UserDetails {
+		user_id: id::User::try_from("not-a-real-user").unwrap(),
+	};
+	let err = api_client.query(query).await.unwrap_err();
+
+	dbg!(&err);
+	assert!(err.is_not_found());
+	assert!(
+		matches!(err, ApiError::Api { ref message, .. } if message == "User not found")
+	);
+}
+
+#[tokio::test]
+async fn unauthorized() {
+	let server = common::mock_server();
+	let api_client = AuthenticatedCVR::new(
+		common::configuration(&server),
+		SavedLoginCredentials {
+			username: "nobody".to_owned(),
+			access_key: "not-a-real-access-key".to_owned(),
+		},
+	)
+	.unwrap();
+
+	let err =
+		api_client.query(chilloutvr::query::FriendList {}).await.unwrap_err();
+
+	dbg!(&err);
+	assert!(err.is_unauthorized());
+	assert!(!err.is_not_found());
+}
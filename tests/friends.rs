@@ -1,14 +1,14 @@
 #![cfg(feature = "http_client")]
 
 use chilloutvr::{
-	api_client::{ApiClient, ApiError},
+	api_client::ApiError,
 	model::{FriendRequests, Friends},
 };
 
 mod common;
 
 #[tokio::test]
-#[ignore]
+#[cfg_attr(not(feature = "mock"), ignore)]
 async fn friend_requests() -> Result<(), ApiError> {
 	let api_client = common::api_client();
 
@@ -34,7 +34,7 @@ async fn friend_requests() -> Result<(), ApiError> {
 }
 
 #[tokio::test]
-#[ignore]
+#[cfg_attr(not(feature = "mock"), ignore)]
 async fn friends() -> Result<(), ApiError> {
 	let api_client = common::api_client();
 
@@ -0,0 +1,149 @@
+#![cfg(feature = "http_client_core")]
+
+use std::{
+	collections::VecDeque,
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+
+use chilloutvr::{
+	api_client::{
+		ApiConfiguration,
+		ApiError,
+		AuthenticatedCVR,
+		HttpBackend,
+		HttpError,
+		HttpRequest,
+		HttpResponse,
+	},
+	query::FriendList,
+};
+mod common;
+
+/// Responds with the queued responses, recording the requests
+#[derive(Debug, Default, Clone)]
+struct Canned {
+	requests: Arc<Mutex<Vec<HttpRequest>>>,
+	responses: Arc<Mutex<VecDeque<Result<HttpResponse, HttpError>>>>,
+}
+
+impl Canned {
+	fn respond(&self, status: u16, body: &str) {
+		self.responses.lock().unwrap().push_back(Ok(
+			http::Response::builder()
+				.status(status)
+				.body(body.as_bytes().to_vec())
+				.unwrap(),
+		));
+	}
+
+	fn fail(&self, err: HttpError) {
+		self.responses.lock().unwrap().push_back(Err(err));
+	}
+
+	fn requests(&self) -> usize { self.requests.lock().unwrap().len() }
+}
+
+#[async_trait::async_trait]
+impl HttpBackend for Canned {
+	async fn execute(
+		&self, request: HttpRequest,
+	) -> Result<HttpResponse, HttpError> {
+		self.requests.lock().unwrap().push(request);
+		self.responses.lock().unwrap().pop_front().unwrap()
+	}
+}
+
+fn client(backend: &Canned) -> AuthenticatedCVR {
+	let mut config = ApiConfiguration::new("chilloutvr tests".to_owned());
+	config.http_url = "http://cvr.invalid/1".to_owned();
+	config.retry.initial_delay = Duration::from_millis(1);
+	AuthenticatedCVR::new(config, common::fake_credentials())
+		.unwrap()
+		.with_http_backend(Arc::new(backend.clone()))
+}
+
+#[tokio::test]
+async fn builds_requests() {
+	let backend = Canned::default();
+	backend.respond(200, r#"{"message": "", "data": []}"#);
+
+	let friends = client(&backend).query(FriendList()).await.unwrap();
+
+	assert!(friends.data.0.is_empty());
+	let requests = backend.requests.lock().unwrap();
+	let request = &requests[0];
+	assert_eq!(request.method(), http::Method::GET);
+	assert_eq!(request.uri(), "http://cvr.invalid/1/friends");
+	assert_eq!(request.headers()["user-agent"], "chilloutvr tests");
+	assert_eq!(request.headers()["username"], "ljoonal");
+	assert_eq!(
+		request.headers()["accesskey"],
+		"an-access-key-that-is-long-enough"
+	);
+	assert!(request.headers().contains_key("platform"));
+}
+
+#[tokio::test]
+async fn error_responses() {
+	let backend = Canned::default();
+	backend.respond(404, r#"{"message": "Nothing here", "data": null}"#);
+
+	let err = client(&backend).query(FriendList()).await.unwrap_err();
+
+	assert!(
+		matches!(err, ApiError::Api { ref message, .. } if message == "Nothing here")
+	);
+	assert!(err.is_not_found());
+}
+
+#[tokio::test]
+async fn retries_transient_errors() {
+	let backend = Canned::default();
+	backend.fail(HttpError::transient("connection refused"));
+	backend.respond(200, r#"{"message": "", "data": []}"#);
+
+	client(&backend).query(FriendList()).await.unwrap();
+
+	assert_eq!(backend.requests(), 2);
+}
+
+#[tokio::test]
+async fn does_not_retry_other_errors() {
+	let backend = Canned::default();
+	backend.fail(HttpError::new("invalid certificate"));
+
+	let err = client(&backend).query(FriendList()).await.unwrap_err();
+
+	assert!(matches!(err, ApiError::Http(ref err) if !err.is_transient()));
+	assert_eq!(backend.requests(), 1);
+}
+
+#[cfg(all(feature = "ureq", feature = "mock"))]
+#[tokio::test]
+async fn ureq() {
+	use chilloutvr::{
+		api_client::{UnauthenticatedCVR, UreqBackend},
+		id,
+		query::UserDetails,
+	};
+
+	let server = common::mock_server();
+	let account = server.fixtures().accounts[0].clone();
+	let api_client = UnauthenticatedCVR::new(common::configuration(&server))
+		.unwrap()
+		.with_http_backend(Arc::new(UreqBackend::default()));
+
+	let (api_client, user_auth) =
+		api_client.login(account.credentials).await.unwrap();
+	assert_eq!(user_auth, account.auth);
+
+	let friends = api_client.query(FriendList()).await.unwrap();
+	assert_eq!(friends.data, server.fixtures().friends);
+
+	let err = api_client
+		.query(UserDetails { user_id: id::User::try_from("nobody").unwrap() })
+		.await
+		.unwrap_err();
+	assert!(err.is_not_found());
+}
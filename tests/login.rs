@@ -1,34 +1,41 @@
 #![cfg(feature = "http_client")]
 
-use chilloutvr::{
-	api_client::{ApiClient, ApiError},
-	query::AuthType,
-};
+use chilloutvr::{api_client::ApiError, query::AuthType};
 
 mod common;
 
 #[tokio::test]
-#[ignore]
+#[cfg_attr(not(feature = "mock"), ignore)]
 async fn login() -> Result<(), ApiError> {
-	let client = common::unauthenticated_api_client();
+	#[cfg(not(feature = "mock"))]
+	let (client, credentials) = {
+		/*
+		example credentials file:
 
-	/*
-	example credentials file:
+		{
+				"auth_type": "loginProfile",
+				"username": "example@example.com",
+				"password": "hunter2"
+		}
 
-	{
-			"auth_type": "loginProfile",
-			"username": "example@example.com",
-			"password": "hunter2"
-	}
+		*/
 
-	*/
+		let credentials = serde_json::from_slice::<AuthType>(
+			&std::fs::read("user-credentials.json").expect(
+				"must have a prepared `user-credentials.json` file for live API testing",
+			),
+		)
+		.expect("`user-credentials.json` file to parse into auth type");
 
-	let credentials = serde_json::from_slice::<AuthType>(
-		&std::fs::read("user-credentials.json").expect(
-			"must have a prepared `user-credentials.json` file for live API testing",
-		),
-	)
-	.expect("`user-credentials.json` file to parse into auth type");
+		(common::unauthenticated_api_client(), credentials)
+	};
+	#[cfg(feature = "mock")]
+	let (client, credentials) = {
+		let server = common::mock_server();
+		let credentials =
+			AuthType::LoginProfile(server.fixtures().accounts[0].credentials.clone());
+		(common::unauthenticated_client(&server), credentials)
+	};
 
 	let results = client.query(credentials).await?.data;
 
@@ -38,3 +45,37 @@ async fn login() -> Result<(), ApiError> {
 
 	Ok(())
 }
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn login_flow() -> Result<(), ApiError> {
+	let server = common::mock_server();
+	let account = server.fixtures().accounts[0].clone();
+
+	let (client, user_auth) =
+		common::unauthenticated_client(&server).login(account.credentials).await?;
+
+	assert_eq!(user_auth.user_id, account.auth.user_id);
+	assert_eq!(user_auth.current_avatar, account.auth.current_avatar);
+	client.query(chilloutvr::query::FriendList {}).await?;
+
+	Ok(())
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn invalid_credentials() {
+	let server = common::mock_server();
+
+	let Err(err) = common::unauthenticated_client(&server)
+		.login(chilloutvr::query::LoginCredentials {
+			email: "mock@example.com".to_owned(),
+			password: "wrong".to_owned(),
+		})
+		.await
+	else {
+		panic!("logging in with the wrong password should fail");
+	};
+
+	assert!(matches!(err, ApiError::InvalidCredentials(_)), "{err:?}");
+}
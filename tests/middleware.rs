@@ -0,0 +1,122 @@
+#![cfg(feature = "mock")]
+
+use std::sync::{Arc, Mutex};
+
+use chilloutvr::{
+	api_client::{ApiError, HttpRequest, Middleware, ResponseInfo},
+	id,
+	query::{FriendList, Online, UserDetails},
+};
+use tokio_stream::StreamExt;
+mod common;
+
+#[derive(Debug, Default, Clone)]
+struct Recorder(Arc<Mutex<Vec<String>>>);
+
+impl Recorder {
+	fn record(&self, event: String) { self.0.lock().unwrap().push(event); }
+
+	fn events(&self) -> Vec<String> { self.0.lock().unwrap().clone() }
+}
+
+#[async_trait::async_trait]
+impl Middleware for Recorder {
+	async fn before_request(&self, request: &mut HttpRequest) {
+		request.headers_mut().insert("x-signature", "signed".parse().unwrap());
+		self.record(format!("request {}", request.uri().path()));
+	}
+
+	fn after_response(&self, response: &ResponseInfo<'_>) {
+		self.record(format!("response {}", response.status.as_u16()));
+	}
+
+	fn on_error(&self, error: &ApiError) {
+		self.record(format!("error {:?}", error.status().map(|s| s.as_u16())));
+	}
+
+	fn on_ws_send(&self, _frame: &[u8]) { self.record("ws send".to_owned()); }
+
+	fn on_ws_receive(&self, _frame: &[u8]) {
+		self.record("ws receive".to_owned());
+	}
+}
+
+#[tokio::test]
+async fn http() {
+	let server = common::mock_server();
+	let recorder = Recorder::default();
+	let api_client =
+		common::authenticated_client(&server).with_middleware(recorder.clone());
+
+	api_client.query(FriendList {}).await.unwrap();
+	api_client
+		.query(UserDetails { user_id: id::User::try_from("nobody").unwrap() })
+		.await
+		.unwrap_err();
+
+	assert_eq!(
+		recorder.events(),
+		[
+			"request /1/friends",
+			"response 200",
+			"request /1/users/nobody",
+			"response 404",
+			"error Some(404)",
+		]
+	);
+	assert!(
+		server
+			.requests()
+			.iter()
+			.all(|request| request.headers["x-signature"] == "signed")
+	);
+}
+
+#[tokio::test]
+async fn replayed_after_reauthentication() {
+	let server = common::mock_server();
+	let login = server.fixtures().accounts[0].credentials.clone();
+	let recorder = Recorder::default();
+	let api_client = common::authenticated_client(&server)
+		.with_reauthentication(Some(login))
+		.with_middleware(recorder.clone());
+	server.update_fixtures(|fixtures| {
+		"a-new-access-key-that-is-long-enough"
+			.clone_into(&mut fixtures.accounts[0].auth.access_key);
+	});
+
+	api_client.query(FriendList {}).await.unwrap();
+
+	// The rejection isn't an error, as the replayed query succeeds
+	assert_eq!(
+		recorder.events(),
+		[
+			"request /1/friends",
+			"response 401",
+			"request /1/users/auth",
+			"response 200",
+			"request /1/friends",
+			"response 200",
+		]
+	);
+}
+
+#[tokio::test]
+async fn ws() {
+	let server = common::mock_server();
+	let recorder = Recorder::default();
+	let api_client =
+		common::authenticated_client(&server).with_middleware(recorder.clone());
+
+	let mut listener = api_client.listen().await.unwrap();
+	listener.next().await.unwrap().unwrap();
+	api_client.send(Online).await.unwrap();
+	// Sending happens in the background
+	while server.ws_messages().is_empty() {
+		tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+	}
+
+	let events = recorder.events();
+	assert!(events.contains(&"ws receive".to_owned()));
+	assert!(events.contains(&"ws send".to_owned()));
+}
@@ -0,0 +1,71 @@
+#![cfg(feature = "mock")]
+
+use std::{num::NonZeroU32, time::Duration};
+
+use chilloutvr::{
+	api_client::{
+		AuthenticatedCVR,
+		RateLimitQuota,
+		RateLimits,
+		SharedRateLimiter,
+	},
+	query::{FriendList, Search},
+};
+mod common;
+
+#[tokio::test]
+async fn separate_search_bucket() {
+	let server = common::mock_server();
+	let mut config = common::configuration(&server);
+	let one = NonZeroU32::new(1).unwrap();
+	config.rate_limits.search = Some(RateLimitQuota::new(one, one));
+	let api_client =
+		AuthenticatedCVR::new(config, server.fixtures().accounts[0].auth.clone())
+			.unwrap();
+
+	let search = || Search { term: "club".to_owned() };
+	api_client.query(search()).await.unwrap();
+
+	// The search bucket is empty, but that mustn't stall other requests
+	let second_search = tokio::time::timeout(
+		Duration::from_millis(200),
+		api_client.query(search()),
+	)
+	.await;
+	assert!(second_search.is_err());
+	tokio::time::timeout(
+		Duration::from_millis(200),
+		api_client.query(FriendList {}),
+	)
+	.await
+	.unwrap()
+	.unwrap();
+}
+
+#[tokio::test]
+async fn shared_between_clients() {
+	let server = common::mock_server();
+	let one = NonZeroU32::new(1).unwrap();
+	let mut limits = RateLimits::default();
+	limits.search = Some(RateLimitQuota::new(one, one));
+	let rate_limiter = SharedRateLimiter::new(limits);
+
+	let client = || {
+		AuthenticatedCVR::with_rate_limiter(
+			common::configuration(&server),
+			server.fixtures().accounts[0].auth.clone(),
+			&rate_limiter,
+		)
+		.unwrap()
+	};
+	let first = client();
+	let second = client();
+
+	first.query(Search { term: "club".to_owned() }).await.unwrap();
+	let search = tokio::time::timeout(
+		Duration::from_millis(200),
+		second.query(Search { term: "club".to_owned() }),
+	)
+	.await;
+	assert!(search.is_err());
+}
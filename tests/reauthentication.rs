@@ -0,0 +1,122 @@
+#![cfg(feature = "mock")]
+
+use std::{sync::Arc, time::Duration};
+
+use chilloutvr::{
+	api_client::{
+		ApiError,
+		AuthenticatedCVR,
+		CredentialStore,
+		MemoryCredentialStore,
+	},
+	mock::MockServer,
+	query::{FriendList, SavedLoginCredentials},
+};
+mod common;
+
+const NEW_ACCESS_KEY: &str = "a-new-access-key-that-is-long-enough";
+
+fn rotate_access_key(server: &MockServer) {
+	server.update_fixtures(|fixtures| {
+		NEW_ACCESS_KEY.clone_into(&mut fixtures.accounts[0].auth.access_key);
+	});
+}
+
+#[tokio::test]
+async fn replays_after_login() {
+	let server = common::mock_server();
+	let login = server.fixtures().accounts[0].credentials.clone();
+	let api_client =
+		common::authenticated_client(&server).with_reauthentication(Some(login));
+	rotate_access_key(&server);
+
+	api_client.query(FriendList {}).await.unwrap();
+
+	assert_eq!(api_client.credentials().access_key, NEW_ACCESS_KEY);
+	let paths: Vec<_> =
+		server.requests().into_iter().map(|request| request.path).collect();
+	assert_eq!(paths, ["/1/friends", "/1/users/auth", "/1/friends"]);
+	assert_eq!(server.requests()[2].headers["accesskey"], NEW_ACCESS_KEY);
+}
+
+#[tokio::test]
+async fn disabled_by_default() {
+	let server = common::mock_server();
+	let api_client = common::authenticated_client(&server);
+	rotate_access_key(&server);
+
+	let err = api_client.query(FriendList {}).await.unwrap_err();
+
+	assert!(err.is_unauthorized());
+	assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn logs_in_with_the_stored_credentials() {
+	let server = common::mock_server();
+	let saved = SavedLoginCredentials::from(&server.fixtures().accounts[0].auth);
+	let store = Arc::new(MemoryCredentialStore::default());
+	store.save("main", &saved).unwrap();
+	let api_client = AuthenticatedCVR::from_store(
+		common::configuration(&server),
+		store.clone(),
+		"main",
+	)
+	.unwrap()
+	.with_reauthentication(None);
+	rotate_access_key(&server);
+	// As if another process had logged in
+	let renewed =
+		SavedLoginCredentials { access_key: NEW_ACCESS_KEY.to_owned(), ..saved };
+	store.save("main", &renewed).unwrap();
+
+	api_client.query(FriendList {}).await.unwrap();
+
+	assert_eq!(api_client.credentials(), renewed);
+	let paths: Vec<_> =
+		server.requests().into_iter().map(|request| request.path).collect();
+	assert_eq!(paths, ["/1/friends", "/1/users/auth", "/1/friends"]);
+	assert_eq!(store.load("main").unwrap(), Some(renewed));
+}
+
+#[tokio::test]
+async fn fails_without_other_credentials() {
+	let server = common::mock_server();
+	let api_client =
+		common::authenticated_client(&server).with_reauthentication(None);
+	rotate_access_key(&server);
+
+	let err = api_client.query(FriendList {}).await.unwrap_err();
+
+	assert!(matches!(err, ApiError::InvalidCredentials(_)), "{err:?}");
+	// The query isn't replayed after logging in fails
+	let paths: Vec<_> =
+		server.requests().into_iter().map(|request| request.path).collect();
+	assert_eq!(paths, ["/1/friends", "/1/users/auth"]);
+}
+
+#[tokio::test]
+async fn reconnects_ws() {
+	let server = common::mock_server();
+	let login = server.fixtures().accounts[0].credentials.clone();
+	let api_client =
+		common::authenticated_client(&server).with_reauthentication(Some(login));
+	api_client.ws_connect().await.unwrap();
+	rotate_access_key(&server);
+
+	api_client.query(FriendList {}).await.unwrap();
+
+	let reconnected = || {
+		server.requests().iter().any(|request| {
+			request.path == "/1/users/ws"
+				&& request.headers["accesskey"] == NEW_ACCESS_KEY
+		})
+	};
+	tokio::time::timeout(Duration::from_secs(5), async {
+		while !reconnected() {
+			tokio::time::sleep(Duration::from_millis(10)).await;
+		}
+	})
+	.await
+	.unwrap();
+}
@@ -0,0 +1,67 @@
+#![cfg(feature = "ws")]
+
+use chilloutvr::query::{RequestType, RequestWrapper};
+
+const CODES: [(RequestType, u64); 16] = [
+	(RequestType::SelfOnline, 0),
+	(RequestType::FriendRequestSend, 5),
+	(RequestType::FriendRequestAccept, 6),
+	(RequestType::FriendRequestDecline, 7),
+	(RequestType::UnFriend, 8),
+	(RequestType::InviteSend, 10),
+	(RequestType::InviteExpire, 11),
+	(RequestType::RequestInvite, 15),
+	(RequestType::RequestInviteAccept, 16),
+	(RequestType::RequestInviteDecline, 17),
+	(RequestType::BlockUser, 30),
+	(RequestType::UnBlockUser, 31),
+	(RequestType::GlobalMessage, 100),
+	(RequestType::UserMessage, 101),
+	(RequestType::SystemGlobalAnnouncement, 110),
+	(RequestType::SystemUserAnnouncement, 111),
+];
+
+#[test]
+fn serialized_as_codes() {
+	for (request_type, code) in CODES {
+		assert_eq!(
+			serde_json::to_value(request_type).unwrap(),
+			code,
+			"{request_type}"
+		);
+	}
+}
+
+#[test]
+fn deserialized_from_codes_and_names() {
+	for (request_type, code) in CODES {
+		assert_eq!(
+			serde_json::from_value::<RequestType>(code.into()).unwrap(),
+			request_type
+		);
+		assert_eq!(
+			serde_json::from_value::<RequestType>(request_type.as_ref().into())
+				.unwrap(),
+			request_type
+		);
+	}
+}
+
+#[test]
+fn unknown_request_types() {
+	assert!(serde_json::from_str::<RequestType>("1").is_err());
+	assert!(serde_json::from_str::<RequestType>("-1").is_err());
+	assert!(serde_json::from_str::<RequestType>(r#""Unknown""#).is_err());
+}
+
+#[test]
+fn wrapped_requests() {
+	let request =
+		RequestWrapper { request_type: RequestType::InviteSend, data: () };
+	let json = serde_json::to_string(&request).unwrap();
+	assert_eq!(json, r#"{"requestType":10,"data":null}"#);
+	assert_eq!(
+		serde_json::from_str::<RequestWrapper<()>>(&json).unwrap(),
+		request
+	);
+}
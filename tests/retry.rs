@@ -0,0 +1,83 @@
+#![cfg(feature = "mock")]
+
+use std::time::{Duration, Instant};
+
+use chilloutvr::{
+	api_client::{AuthenticatedCVR, UnauthenticatedCVR},
+	mock::MockServer,
+	query::{AuthType, FriendList},
+};
+mod common;
+
+fn client(server: &MockServer, max_retries: u32) -> AuthenticatedCVR {
+	common::client_with(server, |config| {
+		config.retry.max_retries = max_retries;
+		config.retry.initial_delay = Duration::from_millis(10);
+	})
+}
+
+#[tokio::test]
+async fn retries_until_success() {
+	let server = common::mock_server();
+	let api_client = client(&server, 3);
+	server.fail_next(503, None);
+	server.fail_next(429, Some("0"));
+
+	let friends = api_client.query(FriendList {}).await.unwrap();
+
+	assert!(!friends.data.0.is_empty());
+	assert_eq!(server.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn gives_up() {
+	let server = common::mock_server();
+	let api_client = client(&server, 1);
+	server.fail_next(500, None);
+	server.fail_next(500, None);
+
+	let err = api_client.query(FriendList {}).await.unwrap_err();
+
+	assert_eq!(err.status().map(|s| s.as_u16()), Some(500));
+	assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn honors_retry_after() {
+	let server = common::mock_server();
+	let api_client = client(&server, 3);
+	server.fail_next(429, Some("1"));
+
+	let start = Instant::now();
+	api_client.query(FriendList {}).await.unwrap();
+
+	assert!(start.elapsed() >= Duration::from_secs(1));
+	assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn too_long_retry_after_is_an_error() {
+	let server = common::mock_server();
+	let api_client = client(&server, 3);
+	server.fail_next(429, Some("3600"));
+
+	let err = api_client.query(FriendList {}).await.unwrap_err();
+
+	assert_eq!(err.status().map(|s| s.as_u16()), Some(429));
+	assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn login_is_never_retried() {
+	let server = common::mock_server();
+	let api_client =
+		UnauthenticatedCVR::new(common::configuration(&server)).unwrap();
+	server.fail_next(503, Some("0"));
+
+	let credentials =
+		AuthType::LoginProfile(server.fixtures().accounts[0].credentials.clone());
+	let err = api_client.query(credentials).await.unwrap_err();
+
+	assert_eq!(err.status().map(|s| s.as_u16()), Some(503));
+	assert_eq!(server.requests().len(), 1);
+}
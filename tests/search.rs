@@ -1,14 +1,10 @@
 #![cfg(feature = "http_client")]
 
-use chilloutvr::{
-	api_client::{ApiClient, ApiError},
-	id,
-	model::SearchResults,
-};
+use chilloutvr::{api_client::ApiError, id, model::SearchResults};
 mod common;
 
 #[tokio::test]
-#[ignore]
+#[cfg_attr(not(feature = "mock"), ignore)]
 async fn search() -> Result<(), ApiError> {
 	let api_client = common::api_client();
 
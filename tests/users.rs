@@ -1,14 +1,10 @@
 #![cfg(feature = "http_client")]
 
-use chilloutvr::{
-	api_client::{ApiClient, ApiError},
-	id,
-	model::UserDetails,
-};
+use chilloutvr::{api_client::ApiError, id, model::UserDetails};
 mod common;
 
 #[tokio::test]
-#[ignore]
+#[cfg_attr(not(feature = "mock"), ignore)]
 async fn user() -> Result<(), ApiError> {
 	let api_client = common::api_client();
 
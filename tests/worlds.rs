@@ -1,13 +1,10 @@
 #![cfg(feature = "http_client")]
 
-use chilloutvr::{
-	api_client::{ApiClient, ApiError},
-	model::WorldListItem,
-};
+use chilloutvr::{api_client::ApiError, model::WorldListItem};
 mod common;
 
 #[tokio::test]
-#[ignore]
+#[cfg_attr(not(feature = "mock"), ignore)]
 async fn active_worlds() -> Result<(), ApiError> {
 	let api_client = common::api_client();
 
@@ -5,7 +5,7 @@ use tokio_stream::StreamExt;
 mod common;
 
 #[tokio::test]
-#[ignore]
+#[cfg_attr(not(feature = "mock"), ignore)]
 async fn online() -> Result<(), ApiError> {
 	let api_client = common::api_client();
 	api_client.send(Online).await?;
@@ -14,13 +14,12 @@ async fn online() -> Result<(), ApiError> {
 }
 
 #[tokio::test]
-#[ignore]
+#[cfg_attr(not(feature = "mock"), ignore)]
 async fn open_ws() -> Result<(), ApiError> {
 	let api_client = common::api_client();
 
-	let listener = api_client.listen().await.unwrap();
-	let mut listener_lock = (*listener).lock().await;
-	let next = listener_lock
+	let mut listener = api_client.listen().await?;
+	let next = listener
 		.next()
 		.await
 		.expect("WS listener to have next item")
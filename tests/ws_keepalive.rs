@@ -0,0 +1,57 @@
+#![cfg(feature = "ws_client")]
+
+use std::time::Duration;
+
+use chilloutvr::api_client::{MemoryPeer, WsFrame};
+use tokio::time::Instant;
+mod common;
+
+const INTERVAL: Duration = Duration::from_secs(30);
+
+/// Waits for the next frame, checking that it's a keepalive
+async fn keepalive(peer: &mut MemoryPeer) {
+	let Some(WsFrame::Binary(frame)) = peer.receive().await else {
+		panic!("expected a binary frame");
+	};
+	let frame: serde_json::Value = serde_json::from_slice(&frame).unwrap();
+	assert_eq!(frame["requestType"], 0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn keeps_sending_online() {
+	let (api_client, server) = common::memory_client(|config| {
+		config.ws_keepalive_interval = Some(INTERVAL);
+	});
+	api_client.ws_connect().await.unwrap();
+	let mut peer = server.accept().await.unwrap();
+
+	// The first one is sent right away
+	let start = Instant::now();
+	keepalive(&mut peer).await;
+	assert!(start.elapsed() < INTERVAL);
+	for _ in 0..2 {
+		let start = Instant::now();
+		keepalive(&mut peer).await;
+		assert!(start.elapsed() >= INTERVAL);
+	}
+
+	// Continues after reconnecting
+	peer.close();
+	let mut peer = server.accept().await.unwrap();
+	keepalive(&mut peer).await;
+	let start = Instant::now();
+	keepalive(&mut peer).await;
+	assert!(start.elapsed() >= INTERVAL);
+
+	// And stops once disconnected
+	api_client.ws_disconnect().await;
+	let closed = tokio::time::timeout(INTERVAL * 3, async {
+		while peer.receive().await.is_some() {}
+	})
+	.await;
+	assert!(closed.is_ok(), "the connection should be closed");
+	assert!(
+		tokio::time::timeout(INTERVAL * 3, server.accept()).await.is_err(),
+		"no new connection should be opened"
+	);
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "ws_client")]
+
+use chilloutvr::{
+	api_client::{ApiError, WsFrame, WsListener},
+	model::{WsResponse, WsResponseData},
+};
+use tokio_stream::StreamExt;
+mod common;
+
+/// An update with the message, to tell the updates apart
+fn update(message: &str) -> WsFrame {
+	WsFrame::Text(format!(
+		r#"{{"responseType": 15, "message": "{message}", "data": []}}"#
+	))
+}
+
+async fn next(listener: &mut WsListener) -> Result<WsResponse, ApiError> {
+	listener.next().await.unwrap()
+}
+
+#[tokio::test]
+async fn every_listener_gets_every_message() {
+	let (api_client, server) = common::memory_client(|_| {});
+	let mut first = api_client.listen().await.unwrap();
+	let mut second = api_client.listen().await.unwrap();
+	let peer = server.accept().await.unwrap();
+	let mut third = api_client.listen().await.unwrap();
+
+	for message in ["first", "second"] {
+		peer.send(update(message)).unwrap();
+	}
+
+	// Buffered for each listener, so they can be read one after another
+	for listener in [&mut first, &mut second, &mut third] {
+		for message in ["first", "second"] {
+			let response = next(listener).await.unwrap();
+			assert!(matches!(response.data, WsResponseData::Invites(_)));
+			assert_eq!(response.message, message);
+		}
+	}
+}
+
+#[tokio::test]
+async fn slow_listener_lags_and_recovers() {
+	let (api_client, server) = common::memory_client(|config| {
+		config.ws_listener_capacity = 2;
+	});
+	let mut slow = api_client.listen().await.unwrap();
+	let mut fast = api_client.listen().await.unwrap();
+	let peer = server.accept().await.unwrap();
+
+	for message in ["1", "2", "3", "4", "5"] {
+		peer.send(update(message)).unwrap();
+		// Keeps up, so every message has been received once this returns
+		assert_eq!(next(&mut fast).await.unwrap().message, message);
+	}
+
+	assert!(matches!(next(&mut slow).await, Err(ApiError::Lagged(3))));
+	assert_eq!(next(&mut slow).await.unwrap().message, "4");
+	assert_eq!(next(&mut slow).await.unwrap().message, "5");
+
+	peer.send(update("6")).unwrap();
+	assert_eq!(next(&mut slow).await.unwrap().message, "6");
+	assert_eq!(next(&mut fast).await.unwrap().message, "6");
+}
@@ -0,0 +1,223 @@
+#![cfg(feature = "mock")]
+
+use std::time::Duration;
+
+use chilloutvr::{
+	api_client::{ApiError, MemoryPeer, WsFrame, WsOutcome},
+	id,
+	model::{AssetBase, FriendRequests, WsResponse, WsResponseData},
+	query::{
+		AcceptFriendRequest,
+		DeclineFriendRequest,
+		FriendRequest,
+		Invite,
+		InviteRequest,
+	},
+};
+mod common;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const FRIEND_ID: &str = "6d8ea1b0-1e62-4bca-9b06-e3b2e47c9c7b";
+const REQUESTER_ID: &str = "f5c8d7a1-4e0b-4a55-8d2c-7b1b1d0f6c3e";
+
+#[tokio::test]
+async fn friend_request_is_unconfirmed() {
+	let server = common::mock_server();
+	let api_client = common::authenticated_client(&server);
+
+	// The API responds with the received friend requests, which don't tell
+	// whether the sent one went through
+	let outcome = api_client
+		.send_and_confirm(
+			FriendRequest { id: id::Invite::try_from(FRIEND_ID).unwrap() },
+			Duration::from_millis(200),
+		)
+		.await
+		.unwrap();
+
+	assert_eq!(outcome, WsOutcome::Unconfirmed);
+}
+
+#[tokio::test]
+async fn accepted_friend_request_is_removed() {
+	let server = common::mock_server();
+	let api_client = common::authenticated_client(&server);
+
+	let outcome = api_client
+		.send_and_confirm(
+			AcceptFriendRequest { id: id::Invite::try_from(REQUESTER_ID).unwrap() },
+			TIMEOUT,
+		)
+		.await
+		.unwrap();
+
+	let WsOutcome::Confirmed(response) = outcome else {
+		panic!("expected a confirmation, got {outcome:?}");
+	};
+	let WsResponseData::FriendRequest(requests) = response.data else {
+		panic!("expected a friend request update, got {response:?}");
+	};
+	assert!(requests.0.is_empty());
+}
+
+#[tokio::test]
+async fn popup_rejects_the_request() {
+	let server = common::mock_server();
+	let api_client = common::authenticated_client(&server);
+
+	let err = api_client
+		.send_and_confirm(
+			Invite { id: id::User::try_from("no-such-user").unwrap() },
+			TIMEOUT,
+		)
+		.await
+		.unwrap_err();
+
+	let ApiError::Rejected(response) = err else {
+		panic!("expected a rejection, got {err:?}");
+	};
+	assert_eq!(response.message, "User not found: no-such-user");
+	let WsResponseData::MenuPopup(popup) = &response.data else {
+		panic!("expected a popup, got {:?}", response.data);
+	};
+	assert_eq!(popup.parsed().unwrap().message, "User not found: no-such-user");
+}
+
+#[tokio::test]
+async fn invite_is_unconfirmed() {
+	let server = common::mock_server();
+	let api_client = common::authenticated_client(&server);
+
+	let outcome = api_client
+		.send_and_confirm(
+			Invite { id: id::User::try_from(FRIEND_ID).unwrap() },
+			Duration::from_millis(200),
+		)
+		.await
+		.unwrap();
+
+	assert_eq!(outcome, WsOutcome::Unconfirmed);
+}
+
+#[tokio::test]
+async fn times_out_without_a_response() {
+	let server = common::mock_server();
+	let api_client = common::authenticated_client(&server);
+
+	let err = api_client
+		.send_and_confirm(
+			InviteRequest { id: id::User::try_from(FRIEND_ID).unwrap() },
+			Duration::from_millis(200),
+		)
+		.await
+		.unwrap_err();
+
+	assert!(matches!(err, ApiError::Timeout), "{err:?}");
+	assert_eq!(server.ws_messages().len(), 1);
+}
+
+/// Sends the update to the client
+fn update(peer: &MemoryPeer, message: &str, data: WsResponseData) {
+	let response = WsResponse { message: message.to_owned(), data };
+	let frame = serde_json::to_string(&response).unwrap();
+	peer.send(WsFrame::Text(frame)).unwrap();
+}
+
+#[tokio::test]
+async fn waits_for_the_matching_update() {
+	let (api_client, server) = common::memory_client(|_| {});
+	let requester = AssetBase {
+		id: id::Asset::try_from(REQUESTER_ID).unwrap(),
+		name: "Requester".to_owned(),
+		image_url: String::new(),
+	};
+
+	let respond = async {
+		let mut peer = server.accept().await.unwrap();
+		peer.receive().await.unwrap();
+		let popup = serde_json::json!({
+			"responseType": 0,
+			"message": "",
+			"data": { "message": "Someone else's request failed" },
+		});
+		peer.send(WsFrame::Text(popup.to_string())).unwrap();
+		let requests = FriendRequests(vec![requester.clone()]);
+		update(&peer, "Still there", WsResponseData::FriendRequest(requests));
+		let requests = FriendRequests(Vec::new());
+		update(&peer, "Declined", WsResponseData::FriendRequest(requests));
+		peer
+	};
+	let (response, _peer) = tokio::join!(
+		api_client.send_and_confirm(
+			DeclineFriendRequest { id: id::Invite::try_from(REQUESTER_ID).unwrap() },
+			TIMEOUT,
+		),
+		respond,
+	);
+
+	let Ok(WsOutcome::Confirmed(response)) = response else {
+		panic!("expected a confirmation, got {response:?}");
+	};
+	assert_eq!(response.message, "Declined");
+}
+
+/// Sends the popup to the client in response to the request
+async fn reject_with(
+	request: impl chilloutvr::query::Confirmable
+	+ serde::Serialize
+	+ Clone
+	+ Send
+	+ Sync,
+	popup: serde_json::Value,
+) -> Result<WsOutcome, ApiError> {
+	let (api_client, server) = common::memory_client(|_| {});
+	let respond = async {
+		let mut peer = server.accept().await.unwrap();
+		peer.receive().await.unwrap();
+		let popup = serde_json::json!({
+			"responseType": 0,
+			"message": "",
+			"data": popup,
+		});
+		peer.send(WsFrame::Text(popup.to_string())).unwrap();
+		peer
+	};
+	let (outcome, _peer) = tokio::join!(
+		api_client.send_and_confirm(request, Duration::from_millis(200)),
+		respond,
+	);
+	outcome
+}
+
+#[tokio::test]
+async fn popups_about_other_ids_are_ignored() {
+	let request =
+		DeclineFriendRequest { id: id::Invite::try_from("friend").unwrap() };
+
+	// Only mentions an ID that the request's ID is a part of
+	let popup =
+		serde_json::json!({ "message": "Friend request not found: friends" });
+	let err = reject_with(request.clone(), popup).await.unwrap_err();
+	assert!(matches!(err, ApiError::Timeout), "{err:?}");
+
+	let popup = serde_json::json!({
+		"message": "Friend request not found",
+		"action": { "userId": "friend" },
+	});
+	let err = reject_with(request, popup).await.unwrap_err();
+	assert!(matches!(err, ApiError::Rejected(_)), "{err:?}");
+}
+
+#[tokio::test]
+async fn unconfirmable_requests_can_be_rejected() {
+	let popup =
+		serde_json::json!({ "message": format!("User not found: {FRIEND_ID}") });
+	let err = reject_with(
+		FriendRequest { id: id::Invite::try_from(FRIEND_ID).unwrap() },
+		popup,
+	)
+	.await
+	.unwrap_err();
+
+	assert!(matches!(err, ApiError::Rejected(_)), "{err:?}");
+}
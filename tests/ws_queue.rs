@@ -0,0 +1,138 @@
+#![cfg(feature = "mock")]
+
+use std::time::Duration;
+
+use chilloutvr::{
+	api_client::{AuthenticatedCVR, WsQueueOverflow, WsQueuePolicy},
+	id,
+	mock::MockServer,
+	query::UnFriend,
+};
+mod common;
+
+fn client(
+	server: &MockServer, queue: impl FnOnce(&mut WsQueuePolicy),
+) -> AuthenticatedCVR {
+	common::client_with(server, |config| {
+		common::fast_reconnects(config);
+		queue(&mut config.ws_queue);
+	})
+}
+
+async fn wait_until(condition: impl Fn() -> bool) {
+	tokio::time::timeout(Duration::from_secs(5), async {
+		while !condition() {
+			tokio::time::sleep(Duration::from_millis(10)).await;
+		}
+	})
+	.await
+	.unwrap();
+}
+
+/// Drops the connection, and keeps it down until [`restore`] is called
+async fn disconnect(server: &MockServer) {
+	// Changing the access key makes reconnection attempts fail
+	server.update_fixtures(|fixtures| {
+		fixtures.accounts[0].auth.access_key.push('|');
+	});
+	let attempts = || {
+		server
+			.requests()
+			.iter()
+			.filter(|request| request.path == "/1/users/ws")
+			.count()
+	};
+	let connected = attempts();
+	server.drop_ws_connections();
+	// A failed reconnection attempt means that the drop was noticed
+	wait_until(|| attempts() > connected).await;
+}
+
+fn restore(server: &MockServer) {
+	server.update_fixtures(|fixtures| {
+		fixtures.accounts[0].auth.access_key.pop();
+	});
+}
+
+fn unfriend(id: &'static str) -> UnFriend {
+	UnFriend { id: id::Invite::try_from(id).unwrap() }
+}
+
+fn sent_ids(server: &MockServer) -> Vec<String> {
+	server
+		.ws_messages()
+		.iter()
+		.map(|message| message["data"]["id"].as_str().unwrap().to_owned())
+		.collect()
+}
+
+#[tokio::test]
+async fn flushes_in_order_after_reconnecting() {
+	let server = common::mock_server();
+	let api_client = client(&server, |_| {});
+	api_client.ws_connect().await.unwrap();
+	disconnect(&server).await;
+
+	for id in ["first", "second", "third"] {
+		api_client.send(unfriend(id)).await.unwrap();
+	}
+	restore(&server);
+
+	wait_until(|| server.ws_messages().len() == 3).await;
+	assert_eq!(sent_ids(&server), ["first", "second", "third"]);
+}
+
+#[tokio::test]
+async fn drops_oldest_when_full() {
+	let server = common::mock_server();
+	let api_client = client(&server, |queue| queue.capacity = 2);
+	api_client.ws_connect().await.unwrap();
+	disconnect(&server).await;
+
+	for id in ["first", "second", "third"] {
+		api_client.send(unfriend(id)).await.unwrap();
+	}
+	restore(&server);
+
+	wait_until(|| server.ws_messages().len() == 2).await;
+	tokio::time::sleep(Duration::from_millis(100)).await;
+	assert_eq!(sent_ids(&server), ["second", "third"]);
+}
+
+#[tokio::test]
+async fn drops_newest_when_full() {
+	let server = common::mock_server();
+	let api_client = client(&server, |queue| {
+		queue.capacity = 2;
+		queue.overflow = WsQueueOverflow::DropNewest;
+	});
+	api_client.ws_connect().await.unwrap();
+	disconnect(&server).await;
+
+	for id in ["first", "second", "third"] {
+		api_client.send(unfriend(id)).await.unwrap();
+	}
+	restore(&server);
+
+	wait_until(|| server.ws_messages().len() == 2).await;
+	tokio::time::sleep(Duration::from_millis(100)).await;
+	assert_eq!(sent_ids(&server), ["first", "second"]);
+}
+
+#[tokio::test]
+async fn drops_expired() {
+	let server = common::mock_server();
+	let api_client =
+		client(&server, |queue| queue.max_age = Some(Duration::from_millis(50)));
+	api_client.ws_connect().await.unwrap();
+	disconnect(&server).await;
+
+	api_client.send(unfriend("expired")).await.unwrap();
+	tokio::time::sleep(Duration::from_millis(100)).await;
+	api_client.send(unfriend("fresh")).await.unwrap();
+	restore(&server);
+
+	wait_until(|| !server.ws_messages().is_empty()).await;
+	tokio::time::sleep(Duration::from_millis(100)).await;
+	assert_eq!(sent_ids(&server), ["fresh"]);
+}
@@ -0,0 +1,124 @@
+#![cfg(feature = "ws_client")]
+
+use std::time::{Duration, Instant};
+
+use chilloutvr::{
+	api_client::{AuthenticatedCVR, ConnectionState, WsFrame},
+	query::Online,
+};
+use tokio_stream::StreamExt;
+mod common;
+
+async fn wait_for(
+	api_client: &AuthenticatedCVR, state: impl Fn(&ConnectionState) -> bool,
+) {
+	let mut changes = api_client.ws_state_changes();
+	tokio::time::timeout(Duration::from_secs(5), async {
+		while let Some(current) = changes.next().await {
+			if state(&current) {
+				return;
+			}
+		}
+		panic!("the state stream ended");
+	})
+	.await
+	.unwrap();
+}
+
+#[tokio::test]
+async fn backs_off_between_attempts() {
+	let (api_client, server) = common::memory_client(|config| {
+		config.ws_reconnect.initial_delay = Duration::from_millis(50);
+		config.ws_reconnect.max_delay = Duration::from_secs(1);
+		config.ws_reconnect.multiplier = 2.0;
+		config.ws_reconnect.jitter = 0.0;
+		config.ws_reconnect.max_attempts = Some(2);
+	});
+	api_client.ws_connect().await.unwrap();
+	let peer = server.accept().await.unwrap();
+
+	// Without the server, every reconnection attempt fails
+	let dropped = Instant::now();
+	drop((peer, server));
+	wait_for(&api_client, |state| {
+		matches!(state, ConnectionState::Reconnecting { attempt: 2 })
+	})
+	.await;
+	wait_for(&api_client, |state| {
+		matches!(state, ConnectionState::Disconnected { .. })
+	})
+	.await;
+
+	// 50ms before the first attempt, and 100ms before the second one
+	assert!(dropped.elapsed() >= Duration::from_millis(150));
+}
+
+#[tokio::test]
+async fn reconnects_on_use_after_giving_up() {
+	let (api_client, server) = common::memory_client(|config| {
+		config.ws_reconnect.enabled = false;
+	});
+	api_client.ws_connect().await.unwrap();
+	let mut peer = server.accept().await.unwrap();
+
+	peer.close();
+	wait_for(&api_client, |state| {
+		matches!(state, ConnectionState::Disconnected { .. })
+	})
+	.await;
+	tokio::time::sleep(Duration::from_millis(50)).await;
+	assert!(!api_client.ws_is_connected().await);
+
+	api_client.send(Online).await.unwrap();
+	let mut peer = tokio::time::timeout(Duration::from_secs(5), server.accept())
+		.await
+		.unwrap()
+		.unwrap();
+	assert!(matches!(peer.receive().await, Some(WsFrame::Binary(_))));
+	assert!(api_client.ws_is_connected().await);
+}
+
+#[tokio::test]
+async fn connect_after_giving_up() {
+	let (api_client, server) = common::memory_client(|config| {
+		config.ws_reconnect.max_attempts = Some(1);
+	});
+	api_client.ws_connect().await.unwrap();
+	drop(server);
+
+	wait_for(&api_client, |state| {
+		matches!(state, ConnectionState::Reconnecting { attempt: 1 })
+	})
+	.await;
+	wait_for(&api_client, |state| {
+		matches!(state, ConnectionState::Disconnected { .. })
+	})
+	.await;
+	tokio::time::sleep(Duration::from_millis(50)).await;
+
+	// A new connection is attempted, instead of reusing the stopped client
+	api_client.ws_connect().await.unwrap_err();
+}
+
+#[tokio::test]
+async fn listeners_get_the_reason_for_giving_up() {
+	let (api_client, server) = common::memory_client(|config| {
+		config.ws_reconnect.enabled = false;
+	});
+	let mut listener = api_client.listen().await.unwrap();
+	let mut peer = server.accept().await.unwrap();
+
+	peer.close();
+	let err = tokio::time::timeout(Duration::from_secs(5), listener.next())
+		.await
+		.unwrap()
+		.unwrap()
+		.unwrap_err();
+	let Some(reason) = api_client.ws_last_error() else {
+		panic!("no reason was recorded for the disconnect");
+	};
+	let chilloutvr::api_client::ApiError::WebSocket(err) = err else {
+		panic!("expected a WS error, got {err:?}");
+	};
+	assert_eq!(err.to_string(), reason);
+}
@@ -0,0 +1,119 @@
+#![cfg(feature = "ws")]
+
+use chilloutvr::model::{ResponseType, WsResponseData};
+use serde_json::json;
+
+/// Every response type, with some data of it
+fn samples() -> [(ResponseType, serde_json::Value); 7] {
+	let popup = json!({"message": "Hello"});
+	let invite_request = json!({
+		"id": "invite",
+		"sender": {"id": "sender", "name": "Sender", "imageUrl": ""},
+		"receiverId": "receiver",
+	});
+	[
+		(ResponseType::MenuPopup, popup.clone()),
+		(ResponseType::HudMessage, popup.clone()),
+		(ResponseType::PushNotification, popup),
+		(ResponseType::OnlineFriends, json!([])),
+		(ResponseType::Invites, json!([])),
+		(ResponseType::RequestInvites, invite_request),
+		(ResponseType::FriendRequest, json!([])),
+	]
+}
+
+#[test]
+fn response_type_codes() {
+	let codes = [0, 1, 2, 10, 15, 20, 25];
+	for ((response_type, _), code) in samples().into_iter().zip(codes) {
+		assert_eq!(u64::from(response_type), code, "{response_type}");
+		assert_eq!(ResponseType::try_from(code), Ok(response_type));
+		assert_eq!(serde_json::to_value(response_type).unwrap(), code);
+	}
+	assert_eq!(ResponseType::try_from(99), Err(99));
+}
+
+#[test]
+fn serialized_responses() {
+	for (response_type, data) in samples() {
+		let json = json!({"responseType": u64::from(response_type), "data": data});
+		let response = serde_json::from_value::<WsResponseData>(json).unwrap();
+		assert_eq!(response.response_type(), Ok(response_type));
+
+		let serialized = serde_json::to_value(&response).unwrap();
+		assert_eq!(serialized["responseType"], u64::from(response_type));
+		assert_eq!(
+			serde_json::from_value::<WsResponseData>(serialized).unwrap(),
+			response
+		);
+	}
+}
+
+#[test]
+fn serialized_unknown_responses() {
+	let response =
+		WsResponseData::Unknown { response_type: 99, data: json!({"new": true}) };
+	assert_eq!(response.response_type(), Err(99));
+
+	assert_eq!(
+		serde_json::to_value(&response).unwrap(),
+		json!({"responseType": 99, "data": {"new": true}})
+	);
+}
+
+#[cfg(not(feature = "debug"))]
+#[test]
+fn unknown_response_types() {
+	let data = json!({"responseType": 99, "data": {"new": true}});
+
+	assert_eq!(
+		serde_json::from_value::<WsResponseData>(data).unwrap(),
+		WsResponseData::Unknown { response_type: 99, data: json!({"new": true}) }
+	);
+}
+
+#[cfg(feature = "debug")]
+#[test]
+fn unknown_response_types_are_errors() {
+	let data = json!({"responseType": 99, "data": {"new": true}});
+
+	assert!(serde_json::from_value::<WsResponseData>(data).is_err());
+}
+
+#[test]
+fn notifications() {
+	let data = json!({
+		"responseType": 1,
+		"data": {
+			"headline": "Hi",
+			"message": "Hello",
+			"timestamp": "2024-01-02T03:04:05Z",
+		},
+	});
+
+	let WsResponseData::HudMessage(message) =
+		serde_json::from_value::<WsResponseData>(data).unwrap()
+	else {
+		panic!("expected a HUD message");
+	};
+	let message = message.parsed().unwrap();
+	assert_eq!(message.title.as_deref(), Some("Hi"));
+	assert_eq!(message.message, "Hello");
+	assert!(message.timestamp.is_some());
+}
+
+#[test]
+fn notifications_in_other_shapes() {
+	let data = json!({"responseType": 2, "data": "Hello"});
+
+	let response = serde_json::from_value::<WsResponseData>(data);
+	#[cfg(not(feature = "debug"))]
+	assert_eq!(
+		response.unwrap(),
+		WsResponseData::PushNotification(chilloutvr::model::Notification::Raw(
+			"Hello".into()
+		))
+	);
+	#[cfg(feature = "debug")]
+	assert!(response.is_err());
+}
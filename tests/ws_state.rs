@@ -0,0 +1,81 @@
+#![cfg(feature = "mock")]
+
+use std::time::Duration;
+
+use chilloutvr::api_client::{AuthenticatedCVR, ConnectionState};
+use tokio_stream::StreamExt;
+mod common;
+
+async fn wait_for(
+	api_client: &AuthenticatedCVR, state: impl Fn(&ConnectionState) -> bool,
+) {
+	let mut changes = api_client.ws_state_changes();
+	tokio::time::timeout(Duration::from_secs(5), async {
+		while let Some(current) = changes.next().await {
+			if state(&current) {
+				return;
+			}
+		}
+		panic!("the state stream ended");
+	})
+	.await
+	.unwrap();
+}
+
+#[tokio::test]
+async fn connect_and_disconnect() {
+	let server = common::mock_server();
+	let api_client = common::client_with(&server, common::fast_reconnects);
+	assert_eq!(api_client.ws_state(), ConnectionState::Closed);
+	assert!(!api_client.ws_is_connected().await);
+
+	api_client.ws_connect().await.unwrap();
+	assert_eq!(api_client.ws_state(), ConnectionState::Connected);
+	assert!(api_client.ws_is_connected().await);
+
+	api_client.ws_disconnect().await;
+	assert_eq!(api_client.ws_state(), ConnectionState::Closed);
+	assert_eq!(api_client.ws_last_error(), None);
+}
+
+#[tokio::test]
+async fn reconnects_after_dropping() {
+	let server = common::mock_server();
+	let api_client = common::client_with(&server, common::fast_reconnects);
+	api_client.ws_connect().await.unwrap();
+
+	// Keeps the reconnection attempts failing until the access key is restored
+	server.update_fixtures(|fixtures| {
+		fixtures.accounts[0].auth.access_key.push('|');
+	});
+	server.drop_ws_connections();
+	wait_for(&api_client, |state| {
+		matches!(state, ConnectionState::Reconnecting { attempt } if *attempt > 1)
+	})
+	.await;
+	assert!(!api_client.ws_is_connected().await);
+
+	server.update_fixtures(|fixtures| {
+		fixtures.accounts[0].auth.access_key.pop();
+	});
+	wait_for(&api_client, |state| state == &ConnectionState::Connected).await;
+	assert!(api_client.ws_is_connected().await);
+	assert!(api_client.ws_last_error().is_some());
+}
+
+#[tokio::test]
+async fn failed_connection() {
+	let server = common::mock_server();
+	let api_client = common::client_with(&server, common::fast_reconnects);
+	server.update_fixtures(|fixtures| {
+		fixtures.accounts[0].auth.access_key.push('|');
+	});
+
+	api_client.ws_connect().await.unwrap_err();
+
+	assert!(matches!(
+		api_client.ws_state(),
+		ConnectionState::Disconnected { .. }
+	));
+	assert!(api_client.ws_last_error().is_some());
+}
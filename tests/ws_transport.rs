@@ -0,0 +1,63 @@
+#![cfg(feature = "ws_client")]
+
+use std::time::Duration;
+
+use chilloutvr::{
+	api_client::{ConnectionState, WsFrame},
+	model::WsResponseData,
+	query::Online,
+};
+use tokio_stream::StreamExt;
+mod common;
+
+#[tokio::test]
+async fn handshake() {
+	let (api_client, server) = common::memory_client(|_| {});
+
+	api_client.ws_connect().await.unwrap();
+
+	let peer = server.accept().await.unwrap();
+	assert_eq!(peer.url(), chilloutvr::API_V1_WS_URL);
+	assert_eq!(peer.headers()["username"], "ljoonal");
+	assert_eq!(peer.headers()["accesskey"], "an-access-key-that-is-long-enough");
+}
+
+#[tokio::test]
+async fn send_and_listen() {
+	let (api_client, server) = common::memory_client(|_| {});
+
+	let mut listener = api_client.listen().await.unwrap();
+	let mut peer = server.accept().await.unwrap();
+
+	peer
+		.send(WsFrame::Text(
+			r#"{"responseType": 15, "message": "", "data": []}"#.to_owned(),
+		))
+		.unwrap();
+	let response = listener.next().await.unwrap().unwrap();
+	assert!(matches!(response.data, WsResponseData::Invites(_)));
+
+	api_client.send(Online).await.unwrap();
+	let Some(WsFrame::Binary(frame)) = peer.receive().await else {
+		panic!("expected a binary frame");
+	};
+	let frame: serde_json::Value = serde_json::from_slice(&frame).unwrap();
+	assert_eq!(frame["requestType"], 0);
+}
+
+#[tokio::test]
+async fn reconnects_when_closed() {
+	let (api_client, server) = common::memory_client(|_| {});
+	api_client.ws_connect().await.unwrap();
+	let mut peer = server.accept().await.unwrap();
+
+	peer.close();
+
+	let peer = tokio::time::timeout(Duration::from_secs(5), server.accept())
+		.await
+		.unwrap()
+		.unwrap();
+	assert_eq!(peer.headers()["username"], "ljoonal");
+	let mut changes = api_client.ws_state_changes();
+	while changes.next().await != Some(ConnectionState::Connected) {}
+}